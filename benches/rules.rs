@@ -0,0 +1,33 @@
+//! Compare rule and call lookup in a scanning mock and an indexed mock.
+//!
+//! Run with `cargo bench --bench rules`.
+
+use mock_it::{eq, verify, Matcher, Mock};
+use std::time::{Duration, Instant};
+
+const RULES: usize = 5_000;
+
+fn configure_and_call(mock: Mock<Matcher<usize>, usize>) -> Duration {
+    let start = Instant::now();
+
+    for input in 0..RULES {
+        mock.when(eq(input)).will_return(input);
+    }
+    for input in 0..RULES {
+        assert_eq!(mock.called(eq(input)), input);
+    }
+    for input in 0..RULES {
+        assert!(verify(mock.was_called_with(eq(input)).times(1)));
+    }
+
+    start.elapsed()
+}
+
+fn main() {
+    let scan = configure_and_call(Mock::new("scan".to_string()));
+    let indexed = configure_and_call(Mock::indexed("indexed".to_string()));
+
+    println!("{} rules, configured, called and verified once each", RULES);
+    println!("scan:    {:?}", scan);
+    println!("indexed: {:?}", indexed);
+}
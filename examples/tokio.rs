@@ -49,6 +49,7 @@ pub mod tests {
     }
 }
 
-fn main() {
-    let _ = tests::should_run_as_long_as_it_is_not_finished();
+#[tokio::main]
+async fn main() {
+    tests::should_run_as_long_as_it_is_not_finished().await;
 }
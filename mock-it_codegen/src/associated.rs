@@ -0,0 +1,140 @@
+use crate::attr::MockItArgs;
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::visit_mut::{self, VisitMut};
+use syn::{parse2, Expr, ItemTrait, TraitItem, TraitItemConst, TraitItemType, Type};
+
+/// Concrete types and values of the associated items of a trait
+pub struct AssociatedItems {
+    names: Vec<Ident>,
+    types: Vec<(Ident, Type)>,
+    consts: Vec<(Ident, Type, Expr)>,
+}
+
+impl AssociatedItems {
+    /// Bind every associated type of the trait to the type given in the
+    /// attribute, or to its default, and every associated constant to the
+    /// value given in the attribute. Constants without one keep their default.
+    pub fn new(item_trait: &ItemTrait, args: &MockItArgs) -> syn::Result<Self> {
+        let mut names = Vec::new();
+        let mut types = Vec::new();
+        let mut consts = Vec::new();
+
+        for item in item_trait.items.iter() {
+            match item {
+                TraitItem::Type(item_type) => {
+                    names.push(item_type.ident.clone());
+                    types.push(associated_type(item_type, args)?);
+                }
+                TraitItem::Const(item_const) => {
+                    names.push(item_const.ident.clone());
+                    consts.extend(associated_const(item_const, args)?);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            names,
+            types,
+            consts,
+        })
+    }
+
+    /// Whether the trait has an associated type or constant with the name
+    pub fn contains(&self, ident: &Ident) -> bool {
+        self.names.contains(ident)
+    }
+
+    /// Replace `Self::Type` by the concrete type in the trait, so its methods
+    /// can be mocked outside of the trait implementation
+    pub fn substitute(&self, item_trait: &ItemTrait) -> ItemTrait {
+        let mut item_trait = item_trait.clone();
+        Substitute(&self.types).visit_item_trait_mut(&mut item_trait);
+        item_trait
+    }
+
+    /// The associated item definitions of the trait implementation
+    pub fn definitions(&self) -> Vec<TokenStream> {
+        let types = self
+            .types
+            .iter()
+            .map(|(name, ty)| quote! { type #name = #ty; });
+        let consts = self
+            .consts
+            .iter()
+            .map(|(name, ty, value)| quote! { const #name: #ty = #value; });
+
+        types.chain(consts).collect()
+    }
+}
+
+fn binding<'a>(args: &'a MockItArgs, ident: &Ident) -> Option<&'a TokenStream> {
+    args.bindings
+        .iter()
+        .find(|(name, _value)| name == ident)
+        .map(|(_name, value)| value)
+}
+
+fn associated_type(item_type: &TraitItemType, args: &MockItArgs) -> syn::Result<(Ident, Type)> {
+    let ty = match (binding(args, &item_type.ident), &item_type.default) {
+        (Some(value), _) => parse2(value.clone())?,
+        (None, Some((_eq, default))) => default.clone(),
+        (None, None) => {
+            return Err(syn::Error::new_spanned(
+                item_type,
+                format!(
+                    "associated type `{0}` needs a type, like `#[mock_it({0} = Type)]`",
+                    item_type.ident
+                ),
+            ))
+        }
+    };
+
+    Ok((item_type.ident.clone(), ty))
+}
+
+/// The value of an associated constant, if the mock must define it
+fn associated_const(
+    item_const: &TraitItemConst,
+    args: &MockItArgs,
+) -> syn::Result<Option<(Ident, Type, Expr)>> {
+    let value = match (binding(args, &item_const.ident), &item_const.default) {
+        (Some(value), _) => parse2(value.clone())?,
+        (None, Some(_default)) => return Ok(None),
+        (None, None) => {
+            return Err(syn::Error::new_spanned(
+                item_const,
+                format!(
+                    "associated constant `{0}` needs a value, like `#[mock_it({0} = value)]`",
+                    item_const.ident
+                ),
+            ))
+        }
+    };
+
+    Ok(Some((
+        item_const.ident.clone(),
+        item_const.ty.clone(),
+        value,
+    )))
+}
+
+struct Substitute<'a>(&'a [(Ident, Type)]);
+
+impl VisitMut for Substitute<'_> {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if let Type::Path(path) = ty {
+            let segments = &path.path.segments;
+            if path.qself.is_none() && segments.len() == 2 && segments[0].ident == "Self" {
+                let associated = self.0.iter().find(|(name, _ty)| name == &segments[1].ident);
+                if let Some((_name, concrete)) = associated {
+                    *ty = concrete.clone();
+                    return;
+                }
+            }
+        }
+
+        visit_mut::visit_type_mut(self, ty);
+    }
+}
@@ -0,0 +1,115 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::ToTokens;
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parenthesized, parse_quote, Expr, LitStr, Path, Token, Type, Visibility};
+
+/// Arguments of the `mock_it` attribute
+#[derive(Default)]
+pub struct MockItArgs {
+    /// Generate a single-threaded mock using `LocalMock`
+    pub local: bool,
+    /// Look up rules and calls by the hash of their input
+    pub indexed: bool,
+    /// Name of the mock struct, instead of the trait name followed by "Mock"
+    pub name: Option<Ident>,
+    /// Visibility of the mock struct, instead of the trait visibility
+    pub vis: Option<Visibility>,
+    /// Derives added to the mock struct
+    pub derives: Vec<Path>,
+    /// Path of the mock-it crate, for when it is renamed or re-exported
+    pub krate: Option<Path>,
+    /// Values bound to the associated items of the trait, like `Output = String`
+    pub bindings: Vec<(Ident, TokenStream)>,
+}
+
+impl MockItArgs {
+    /// Path of the mock-it crate used by the generated code
+    pub fn crate_path(&self) -> Path {
+        match &self.krate {
+            Some(krate) => krate.clone(),
+            None => parse_quote! { ::mock_it },
+        }
+    }
+}
+
+impl Parse for MockItArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = MockItArgs::default();
+
+        while !input.is_empty() {
+            let ident = input.call(Ident::parse_any)?;
+            match ident.to_string().as_str() {
+                "local" => args.local = true,
+                "indexed" => args.indexed = true,
+                "name" => args.name = Some(parse_value::<Ident>(input)?),
+                "vis" => args.vis = Some(parse_value::<Visibility>(input)?),
+                "crate" => args.krate = Some(parse_value::<Path>(input)?),
+                "derive" => {
+                    let content;
+                    parenthesized!(content in input);
+                    let derives = Punctuated::<Path, Token![,]>::parse_terminated(&content)?;
+                    for derive in derives {
+                        check_derive(&derive)?;
+                        args.derives.push(derive);
+                    }
+                }
+                _ if input.peek(Token![=]) => {
+                    input.parse::<Token![=]>()?;
+                    args.bindings.push((ident, parse_binding(input)?));
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        format!("unknown mock_it argument `{}`", ident),
+                    ))
+                }
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(args)
+    }
+}
+
+/// Traits the mock struct always implements
+const IMPLEMENTED: [&str; 3] = ["Clone", "Debug", "Default"];
+
+/// Make sure the derive doesn't clash with an implementation of the mock
+fn check_derive(derive: &Path) -> syn::Result<()> {
+    match derive.segments.last() {
+        Some(segment) if IMPLEMENTED.iter().any(|name| segment.ident == name) => {
+            Err(syn::Error::new_spanned(
+                derive,
+                format!(
+                    "the mock already implements `{}`, remove it from `derive`",
+                    segment.ident
+                ),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Parse the string value of a `key = "value"` argument
+fn parse_value<T: Parse>(input: ParseStream) -> syn::Result<T> {
+    input.parse::<Token![=]>()?;
+    let value: LitStr = input.parse()?;
+    value.parse()
+}
+
+/// Parse the value bound to an associated item, which is a type or an
+/// expression depending on the item
+fn parse_binding(input: ParseStream) -> syn::Result<TokenStream> {
+    let fork = input.fork();
+    if fork.parse::<Type>().is_ok() && (fork.is_empty() || fork.peek(Token![,])) {
+        return Ok(input.parse::<Type>()?.into_token_stream());
+    }
+
+    Ok(input.parse::<Expr>()?.into_token_stream())
+}
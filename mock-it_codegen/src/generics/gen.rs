@@ -1,7 +1,6 @@
 use proc_macro2::Ident;
-use quote::quote;
 use std::collections::HashSet;
-use syn::{parse2, Generics, Type, WhereClause, WherePredicate};
+use syn::{Generics, Lifetime, Type, WherePredicate};
 
 pub struct MockItGenerics {
     generics: Generics,
@@ -20,20 +19,15 @@ impl MockItGenerics {
             .collect()
     }
 
+    pub fn lifetimes(&self) -> Vec<Lifetime> {
+        self.generics
+            .lifetimes()
+            .map(|param| param.lifetime.clone())
+            .collect()
+    }
+
     pub fn add_predicate(&mut self, predicate: WherePredicate) {
-        let where_clause: WhereClause = match &self.generics.where_clause {
-            Some(val) => parse2(quote! {
-                #val
-                    #predicate,
-            })
-            .unwrap(),
-            None => parse2(quote! {
-                where
-                    #predicate,
-            })
-            .unwrap(),
-        };
-        self.generics.where_clause = Some(where_clause);
+        self.generics.make_where_clause().predicates.push(predicate);
     }
 
     pub fn find_type_ident(&self, ty: &Type) -> Option<Ident> {
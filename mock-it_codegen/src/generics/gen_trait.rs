@@ -1,7 +1,6 @@
 use crate::generics::MockItGenerics;
 use crate::mock_fn::MockFn;
-use quote::quote;
-use syn::{parse2, Generics};
+use syn::{parse_quote, Generics};
 
 pub struct MockItTraitGenerics {
     generics: MockItGenerics,
@@ -32,7 +31,7 @@ impl MockItTraitGenerics {
 
         input_types
             .into_iter()
-            .map(|ty| parse2(quote! { #ty: Clone + PartialEq + std::fmt::Debug }).unwrap())
+            .map(|ty| parse_quote! { #ty: ::core::clone::Clone + ::core::cmp::PartialEq + ::core::fmt::Debug })
             .for_each(|predicate| self.generics.add_predicate(predicate));
     }
 
@@ -49,7 +48,7 @@ impl MockItTraitGenerics {
 
         output_types
             .into_iter()
-            .map(|ty| parse2(quote! { #ty: Clone + std::fmt::Debug }).unwrap())
+            .map(|ty| parse_quote! { #ty: ::core::clone::Clone + ::core::fmt::Debug })
             .for_each(|predicate| self.generics.add_predicate(predicate));
     }
 }
@@ -1,48 +1,133 @@
 extern crate proc_macro;
 
+mod associated;
+mod attr;
 mod generics;
 mod mock_fn;
 mod trait_method;
 
-use generics::MockItTraitGenerics;
+use associated::AssociatedItems;
+use attr::MockItArgs;
+use generics::{MockItGenerics, MockItTraitGenerics};
 use mock_fn::{mock_fns, MockFn};
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, Generics, Ident, Item};
+use syn::{parse_macro_input, Generics, Ident, Item, Lifetime, Path};
 use trait_method::get_trait_method_types;
 
 /// Generate a mock struct from a trait. The mock struct will be named after the
 /// trait, with "Mock" appended.
+///
+/// Use `#[mock_it(local)]` to generate a single-threaded mock built on
+/// `LocalMock`, for traits whose inputs or outputs aren't `Send`.
+///
+/// Use `#[mock_it(indexed)]` to look up rules and calls by the hash of their
+/// inputs, for tests configuring thousands of rules. The arguments must then
+/// be `Hash + Eq`.
+///
+/// Methods taking `self` by value, or through `Box<Self>`, `Arc<Self>` or
+/// `Pin<&mut Self>`, share the state of the clone they are called on, so keep
+/// a clone of the mock to verify its calls once it is consumed.
+///
+/// Functions without a receiver are backed by mocks registered by module and
+/// name, configured with helpers such as `ATraitMock::when_create_static(...)`.
+/// They are kept per thread, unless a `StaticMocks` is entered: enter a fresh
+/// one in each test when threads are reused between tests, and run the tasks
+/// of the test in its `scope` so that every worker thread sees the same
+/// mocks. Per-thread mocks are dropped when their thread exits, too late to
+/// fail a test, so their expectations can't be verified on drop. They outlive the mock struct, so `verify_no_more_interactions` and
+/// `assert_all_stubs_used` leave them out, and are completed by
+/// `ATraitMock::verify_no_more_interactions_static()` and
+/// `ATraitMock::assert_all_stubs_used_static()`.
+///
+/// The mock struct has the visibility of the trait. Use
+/// `#[mock_it(name = "FakeNurse", vis = "pub(crate)", derive(...))]` to
+/// change its name and visibility, and to add derives to it. The mock always
+/// implements `Clone`, `Debug` and `Default`, so they can't be derived. Use
+/// `#[mock_it(crate = "path::to::mock_it")]` when mock-it is renamed or
+/// re-exported.
+///
+/// Methods with type parameters get a mock per instantiation, configured with
+/// helpers such as `mock.when_get::<User>(...)`. The mocks are kept by type
+/// and clone their outputs, so the type parameters must be bound by the trait
+/// itself with what the inputs and output of a mock need: `'static`,
+/// `PartialEq + Debug + Send` for an input and `Clone + Send` for an output,
+/// like `fn get<T: Clone + Send + 'static>()`.
+/// Methods whose type parameters lack these bounds, like
+/// `fn parse<T: DeserializeOwned>(&self) -> T`, can't be mocked.
+///
+/// Lifetimes of the methods are replaced by `'static` in the outputs the mock
+/// keeps. Arguments can only borrow them through a reference, like `&'a T`,
+/// which the mock keeps as a shared value: the mock can't keep an argument
+/// like `Token<'a>` past the call, so such methods are rejected.
+///
+/// Methods returning references borrowed from `&self`, like `&str`, `&T` or
+/// `Option<&T>`, are configured with shared values, like `Arc<str>`, `Arc<T>`
+/// or `Option<Arc<T>>`. The mock keeps one clone of each value it returns
+/// until it is dropped.
+///
+/// Associated types and constants are bound with the attribute, like
+/// `#[mock_it(Output = String, MAX = 3)]`, unless the trait gives them a
+/// default.
 #[proc_macro_attribute]
 pub fn mock_it(
-    _attr: proc_macro::TokenStream,
+    attr: proc_macro::TokenStream,
     item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
     // Parse the tokens
+    let args: MockItArgs = parse_macro_input!(attr as MockItArgs);
     let input: Item = parse_macro_input!(item as Item);
 
+    expand(args, input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+fn expand(args: MockItArgs, input: Item) -> syn::Result<TokenStream> {
     // Make sure it's a trait
     let item_trait = match input {
         Item::Trait(item_trait) => item_trait,
-        _ => panic!("Only traits can be mocked with the mock_it macro"),
+        item => {
+            return Err(syn::Error::new_spanned(
+                item,
+                "Only traits can be mocked with the mock_it macro",
+            ))
+        }
+    };
+
+    // Mock the methods with the associated types replaced by concrete ones
+    let associated_items = AssociatedItems::new(&item_trait, &args)?;
+    check_bindings(&args, &associated_items)?;
+    let associated_item_definitions = associated_items.definitions();
+
+    let krate = args.crate_path();
+    let trait_method_types =
+        get_trait_method_types(&associated_items.substitute(&item_trait), &krate)?;
+    // Create the mock identifier
+    let trait_ident = &item_trait.ident;
+    let mock_ident = match &args.name {
+        Some(name) => name.clone(),
+        None => Ident::new(&format!("{}Mock", trait_ident), trait_ident.span()),
     };
 
-    let trait_method_types = get_trait_method_types(&item_trait);
-    let mock_fns = mock_fns(trait_method_types.clone());
+    let mock_fns = mock_fns(trait_method_types.clone(), &args, &mock_ident);
     let helper_functions: Vec<TokenStream> = mock_fns
         .iter()
         .map(|mock_fn| mock_fn.helper_functions())
         .collect();
 
-    // Create the mock identifier
-    let trait_ident = &item_trait.ident;
-    let mock_ident = Ident::new(&format!("{}Mock", trait_ident), trait_ident.span());
+    let mock_vis = args.vis.as_ref().unwrap_or(&item_trait.vis);
+    let derives = &args.derives;
 
     // Generate the mock
-    let fields = create_fields(&mock_fns);
-    let field_init = create_field_init(&mock_ident, &mock_fns);
-    let trait_impls = create_trait_impls(&mock_fns);
-    let clone_impl = create_clone_impl(&mock_fns);
+    let lifetimes = MockItGenerics::new(item_trait.generics.clone()).lifetimes();
+    let fields = create_fields(&mock_fns, &lifetimes);
+    let field_init = create_field_init(&mock_fns, &lifetimes);
+    let trait_impls = create_trait_impls(&mock_fns, &krate);
+    let clone_impl = create_clone_impl(&mock_fns, &lifetimes);
+    let verify_no_more_interactions = create_verify_no_more_interactions(&mock_fns);
+    let assert_all_stubs_used = create_assert_all_stubs_used(&mock_fns);
+    let with_history = create_with_history(&mock_fns, &lifetimes, &krate);
     let async_attribute = async_attribute(&mock_fns);
 
     // Configure trait generics
@@ -52,8 +137,8 @@ pub fn mock_it(
     let output = quote! {
         #item_trait
 
-        #[derive(Debug)]
-        pub struct #mock_ident #generics_ty #generics_where {
+        #[derive(::core::fmt::Debug #(, #derives)*)]
+        #mock_vis struct #mock_ident #generics_ty #generics_where {
             #(#fields),*
         }
 
@@ -65,15 +150,21 @@ pub fn mock_it(
             }
 
             #(#helper_functions)*
+
+            #verify_no_more_interactions
+
+            #assert_all_stubs_used
+
+            #with_history
         }
 
-        impl #generics_impl Default for #mock_ident #generics_ty #generics_where {
+        impl #generics_impl ::core::default::Default for #mock_ident #generics_ty #generics_where {
             fn default() -> Self {
                 #mock_ident::new()
             }
         }
 
-        impl #generics_impl std::clone::Clone for #mock_ident #generics_ty #generics_where {
+        impl #generics_impl ::core::clone::Clone for #mock_ident #generics_ty #generics_where {
             fn clone(&self) -> Self {
                 #mock_ident {
                     #(#clone_impl),*
@@ -83,11 +174,27 @@ pub fn mock_it(
 
         #async_attribute
         impl #generics_impl #trait_ident #generics_ty for #mock_ident #generics_ty #generics_where {
+            #(#associated_item_definitions)*
+
             #(#trait_impls)*
         }
     };
 
-    output.into()
+    Ok(output)
+}
+
+/// Make sure every binding of the attribute names an associated item
+fn check_bindings(args: &MockItArgs, associated_items: &AssociatedItems) -> syn::Result<()> {
+    for (name, _value) in args.bindings.iter() {
+        if !associated_items.contains(name) {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("the trait has no associated type or constant `{}`", name),
+            ));
+        }
+    }
+
+    Ok(())
 }
 
 fn configure_trait_generics(mock_fns: &Vec<MockFn>, generics: &Generics) -> Generics {
@@ -99,68 +206,196 @@ fn configure_trait_generics(mock_fns: &Vec<MockFn>, generics: &Generics) -> Gene
 fn async_attribute(mock_fns: &Vec<MockFn>) -> TokenStream {
     for mock_fn in mock_fns.iter() {
         if mock_fn.is_async() {
-            return quote! { #[async_trait::async_trait] }.into();
+            return quote! { #[::async_trait::async_trait] };
         }
     }
 
     quote! {}.into()
 }
 
+/// The mocked methods taking a receiver, whose mocks are fields of the struct
+fn instance_fns(mock_fns: &[MockFn]) -> impl Iterator<Item = &MockFn> {
+    mock_fns.iter().filter(|mock_fn| !mock_fn.is_static())
+}
+
+/// The marker field using the lifetimes of the trait, which the mocked
+/// methods may not use
+fn lifetimes_field() -> Ident {
+    Ident::new("_lifetimes", proc_macro2::Span::call_site())
+}
+
 /// Create the struct fields
-fn create_fields(mock_fns: &Vec<MockFn>) -> Vec<TokenStream> {
-    mock_fns
-        .iter()
+fn create_fields(mock_fns: &[MockFn], lifetimes: &[Lifetime]) -> Vec<TokenStream> {
+    let mut fields: Vec<TokenStream> = instance_fns(mock_fns)
         .map(|mock_fn| {
             let name = mock_fn.name();
-            let return_input_types = mock_fn.return_input_types();
-            let return_output_type = mock_fn.return_output_type();
+            let field_type = mock_fn.field_type();
 
             quote! {
-                #name: mock_it::Mock<#return_input_types, #return_output_type>
+                #name: #field_type
             }
         })
-        .collect()
+        .collect();
+
+    if !lifetimes.is_empty() {
+        let field = lifetimes_field();
+        fields.push(quote! {
+            #field: ::core::marker::PhantomData<(#(&#lifetimes ()),*)>
+        });
+    }
+
+    fields
 }
 
 /// Create the field initializers for the `new` method
-fn create_field_init(mock_ident: &Ident, mock_fns: &Vec<MockFn>) -> Vec<TokenStream> {
-    mock_fns
-        .iter()
+fn create_field_init(mock_fns: &[MockFn], lifetimes: &[Lifetime]) -> Vec<TokenStream> {
+    let mut field_init: Vec<TokenStream> = instance_fns(mock_fns)
         .map(|mock_fn| {
             let name = mock_fn.name();
+            let field_init = mock_fn.field_init();
 
             quote! {
-                #name: mock_it::Mock::new(format!("{}.{}", stringify!(#mock_ident), stringify!(#name)))
+                #name: #field_init
             }
         })
-        .collect()
+        .collect();
+
+    if !lifetimes.is_empty() {
+        let field = lifetimes_field();
+        field_init.push(quote! { #field: ::core::marker::PhantomData });
+    }
+
+    field_init
 }
 
 /// Create the clone implementation
-fn create_clone_impl(mock_fns: &Vec<MockFn>) -> impl Iterator<Item = TokenStream> + '_ {
-    mock_fns.iter().map(|mock_fn| {
-        let ident = &mock_fn.signature().ident;
-        quote! {
-            #ident: self.#ident.clone()
+fn create_clone_impl(mock_fns: &[MockFn], lifetimes: &[Lifetime]) -> Vec<TokenStream> {
+    let mut clone_impl: Vec<TokenStream> = instance_fns(mock_fns)
+        .map(|mock_fn| {
+            let ident = &mock_fn.signature().ident;
+            quote! {
+                #ident: self.#ident.clone()
+            }
+        })
+        .collect();
+
+    if !lifetimes.is_empty() {
+        let field = lifetimes_field();
+        clone_impl.push(quote! { #field: ::core::marker::PhantomData });
+    }
+
+    clone_impl
+}
+
+/// The mocked functions without a receiver, whose mocks are registered
+fn static_fns(mock_fns: &[MockFn]) -> impl Iterator<Item = &MockFn> {
+    mock_fns.iter().filter(|mock_fn| mock_fn.is_static())
+}
+
+/// Create the functions verifying that no mocked method has unverified
+/// calls, one for the methods with a receiver and one for the functions
+/// without, whose mocks outlive the mock struct
+fn create_verify_no_more_interactions(mock_fns: &[MockFn]) -> TokenStream {
+    let names = instance_fns(mock_fns).map(|mock_fn| mock_fn.name());
+    let static_mocks: Vec<TokenStream> = static_fns(mock_fns)
+        .map(|mock_fn| mock_fn.static_mock())
+        .collect();
+    let verify_static = match static_mocks.is_empty() {
+        true => quote! {},
+        false => quote! {
+            pub fn verify_no_more_interactions_static() {
+                #(#static_mocks.verify_no_more_interactions();)*
+            }
+        },
+    };
+
+    quote! {
+        pub fn verify_no_more_interactions(&self) {
+            #(self.#names.verify_no_more_interactions();)*
         }
-    })
+
+        #verify_static
+    }
+}
+
+/// Create the functions verifying that every configured rule was used, like
+/// `create_verify_no_more_interactions`
+fn create_assert_all_stubs_used(mock_fns: &[MockFn]) -> TokenStream {
+    let names = instance_fns(mock_fns).map(|mock_fn| mock_fn.name());
+    let static_mocks: Vec<TokenStream> = static_fns(mock_fns)
+        .map(|mock_fn| mock_fn.static_mock())
+        .collect();
+    let assert_static = match static_mocks.is_empty() {
+        true => quote! {},
+        false => quote! {
+            pub fn assert_all_stubs_used_static() {
+                #(#static_mocks.assert_all_stubs_used();)*
+            }
+        },
+    };
+
+    quote! {
+        pub fn assert_all_stubs_used(&self) {
+            #(self.#names.assert_all_stubs_used();)*
+        }
+
+        #assert_static
+    }
+}
+
+/// Create the builder configuring the call history of every mocked method
+fn create_with_history(mock_fns: &[MockFn], lifetimes: &[Lifetime], krate: &Path) -> TokenStream {
+    let mut fields: Vec<TokenStream> = instance_fns(mock_fns)
+        .map(|mock_fn| {
+            let name = mock_fn.name();
+            quote! { #name: self.#name.with_history(history) }
+        })
+        .collect();
+
+    if !lifetimes.is_empty() {
+        let field = lifetimes_field();
+        fields.push(quote! { #field: ::core::marker::PhantomData });
+    }
+
+    quote! {
+        pub fn with_history(self, history: #krate::History) -> Self {
+            Self {
+                #(#fields),*
+            }
+        }
+    }
 }
 
 /// Create the trait method implementations
-fn create_trait_impls(mock_fns: &Vec<MockFn>) -> impl Iterator<Item = TokenStream> + '_ {
-    mock_fns.iter().map(|mock_fn| {
+fn create_trait_impls<'a>(
+    mock_fns: &'a [MockFn],
+    krate: &'a Path,
+) -> impl Iterator<Item = TokenStream> + 'a {
+    mock_fns.iter().map(move |mock_fn| {
         let called_fn_name = mock_fn.called_fn_name();
+        let turbofish = mock_fn.turbofish();
         let arg_names = mock_fn.args().into_iter().map(|arg| {
             let name = &arg.name;
             quote! {
-                mock_it::Matcher::Val(#name)
+                #krate::Matcher::Val(#name)
             }
         });
         let signature = mock_fn.signature();
+        // Async methods return a future, so the caller location is lost anyway
+        let track_caller = match mock_fn.is_async() {
+            true => quote! {},
+            false => quote! { #[track_caller] },
+        };
+
+        let receiver = match mock_fn.is_static() {
+            true => quote! { Self:: },
+            false => quote! { self. },
+        };
 
         quote! {
+            #track_caller
             #signature {
-                self.#called_fn_name(#(#arg_names),*)
+                #receiver #called_fn_name #turbofish(#(#arg_names),*)
             }
         }
     })
@@ -1,19 +1,122 @@
-use crate::trait_method::{Argument, TraitMethodType};
+use crate::attr::MockItArgs;
+use crate::trait_method::{Argument, Borrowed, TraitMethodType};
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{Ident, Signature, Type};
+use syn::{Ident, Path, Signature, Type, TypeParam};
 
 pub struct MockFn {
     method: TraitMethodType,
+    local: bool,
+    indexed: bool,
+    krate: Path,
+    mock_ident: Ident,
 }
 
-pub fn mock_fns(trait_method_types: Vec<TraitMethodType>) -> Vec<MockFn> {
-    trait_method_types.into_iter().map(MockFn::new).collect()
+pub fn mock_fns(
+    trait_method_types: Vec<TraitMethodType>,
+    args: &MockItArgs,
+    mock_ident: &Ident,
+) -> Vec<MockFn> {
+    trait_method_types
+        .into_iter()
+        .map(|method| MockFn::new(method, args, mock_ident.clone()))
+        .collect()
 }
 
 impl MockFn {
-    pub fn new(method: TraitMethodType) -> Self {
-        Self { method }
+    pub fn new(method: TraitMethodType, args: &MockItArgs, mock_ident: Ident) -> Self {
+        Self {
+            method,
+            local: args.local,
+            indexed: args.indexed,
+            krate: args.crate_path(),
+            mock_ident,
+        }
+    }
+
+    /// Whether the method has no receiver, so its mock is kept per thread
+    /// instead of in a field of the mock struct
+    pub fn is_static(&self) -> bool {
+        !self.method.has_receiver
+    }
+
+    /// The type parameters of the method
+    fn type_params(&self) -> Vec<&TypeParam> {
+        self.method.signature.generics.type_params().collect()
+    }
+
+    /// Whether the method has type parameters, so each instantiation gets its
+    /// own mock, kept by a `GenericMock`
+    pub fn is_generic(&self) -> bool {
+        !self.type_params().is_empty()
+    }
+
+    /// The type of the struct field backing the method
+    pub fn field_type(&self) -> TokenStream {
+        let krate = &self.krate;
+        let return_input_types = self.return_input_types();
+        let output_type = self.return_output_type();
+        let mock_type = self.mock_type();
+        match (self.is_generic(), self.local) {
+            (true, true) => quote! { #krate::LocalGenericMock },
+            (true, false) => quote! { #krate::GenericMock },
+            (false, _) => quote! { #mock_type<#return_input_types, #output_type> },
+        }
+    }
+
+    /// The initial value of the struct field backing the method
+    pub fn field_init(&self) -> TokenStream {
+        let mock_name = self.mock_name();
+        let mock_name = quote! { ::std::string::String::from(#mock_name) };
+        if self.is_generic() {
+            let field_type = self.field_type();
+            return quote! { #field_type::new(#mock_name) };
+        }
+
+        let constructor = self.constructor();
+        let arg_names = self.arg_names();
+        quote! {
+            #constructor(#mock_name).with_arg_names(&[#(#arg_names),*])
+        }
+    }
+
+    /// The function creating the mock backing the method from its name
+    fn constructor(&self) -> TokenStream {
+        let mock_type = self.mock_type();
+        match self.indexed {
+            true => quote! { #mock_type::indexed },
+            false => quote! { #mock_type::new },
+        }
+    }
+
+    /// Name of the mock backing the method, for failure messages
+    fn mock_name(&self) -> String {
+        format!("{}.{}", self.mock_ident, self.name())
+    }
+
+    /// The mock type backing the method
+    fn mock_type(&self) -> TokenStream {
+        let krate = &self.krate;
+        match self.local {
+            true => quote! { #krate::LocalMock },
+            false => quote! { #krate::Mock },
+        }
+    }
+
+    fn when_type(&self) -> TokenStream {
+        let krate = &self.krate;
+        match self.local {
+            true => quote! { #krate::LocalWhen },
+            false => quote! { #krate::When },
+        }
+    }
+
+    fn validator_type(&self) -> TokenStream {
+        let krate = &self.krate;
+        match self.local {
+            true => quote! { #krate::LocalValidator },
+            false => quote! { #krate::Validator },
+        }
     }
 
     pub fn is_async(&self) -> bool {
@@ -41,6 +144,7 @@ impl MockFn {
     }
 
     pub fn return_input_types(&self) -> TokenStream {
+        let krate = &self.krate;
         let output: Vec<TokenStream> = self
             .method
             .args
@@ -49,7 +153,7 @@ impl MockFn {
                 if !arg.is_reference {
                     let ty = &arg.original_type;
                     return quote! {
-                        mock_it::Matcher<#ty>
+                        #krate::Matcher<#ty>
                     };
                 }
 
@@ -73,11 +177,24 @@ impl MockFn {
         let when_fn = self.when_fn();
         let called_fn = self.called_fn();
         let was_called_with_fn = self.was_called_with_fn();
+        if self.is_static() {
+            let static_mock_fn = self.static_mock_fn();
+            return quote! {
+                #static_mock_fn
+                #when_fn
+                #called_fn
+                #was_called_with_fn
+            };
+        }
+        let inspection_fns = self.inspection_fns();
+        let subscription_fns = self.subscription_fns();
 
         let output = quote! {
             #when_fn
             #called_fn
             #was_called_with_fn
+            #inspection_fns
+            #subscription_fns
         };
 
         output.into()
@@ -98,18 +215,180 @@ impl MockFn {
     }
 
     pub fn when_fn_name(&self) -> Ident {
-        let name = self.name();
-        Ident::new(&format!("when_{}", name.to_string()), name.span())
+        self.helper_fn_name("when")
     }
 
     pub fn called_fn_name(&self) -> Ident {
-        let name = self.name();
-        Ident::new(&format!("calling_{}", name.to_string()), name.span())
+        self.helper_fn_name("calling")
     }
 
     pub fn was_called_with_fn_name(&self) -> Ident {
+        self.helper_fn_name("expect")
+    }
+
+    /// Name of a helper function, with a `_static` suffix for the methods
+    /// without a receiver
+    fn helper_fn_name(&self, prefix: &str) -> Ident {
+        let name = self.name();
+        match self.is_static() {
+            true => Ident::new(&format!("{}_{}_static", prefix, name), name.span()),
+            false => Ident::new(&format!("{}_{}", prefix, name), name.span()),
+        }
+    }
+
+    /// The receiver of the helper functions
+    fn helper_receiver(&self) -> TokenStream {
+        match self.is_static() {
+            true => quote! {},
+            false => quote! { &self, },
+        }
+    }
+
+    /// The registered mock backing a function without a receiver, from
+    /// inside the mock struct
+    pub fn static_mock(&self) -> TokenStream {
+        let static_mock_fn_name = self.helper_fn_name("mock");
+        quote! { Self::#static_mock_fn_name() }
+    }
+
+    /// The mock backing the method, from inside the helper functions
+    fn mock_expr(&self) -> TokenStream {
         let name = self.name();
-        Ident::new(&format!("expect_{}", name.to_string()), name.span())
+        if self.is_static() {
+            return self.static_mock();
+        }
+        if !self.is_generic() {
+            return quote! { self.#name };
+        }
+
+        let params = self.type_param_idents();
+        let key = match params.len() {
+            1 => quote! { #(#params)* },
+            _ => quote! { (#(#params),*) },
+        };
+        let return_input_types = self.return_input_types();
+        let output_type = self.return_output_type();
+        let constructor = self.constructor();
+        let arg_names = self.arg_names();
+        quote! {
+            self.#name.of::<#key, #return_input_types, #output_type>(|name| {
+                #constructor(name).with_arg_names(&[#(#arg_names),*])
+            })
+        }
+    }
+
+    fn type_param_idents(&self) -> Vec<Ident> {
+        self.type_params()
+            .into_iter()
+            .map(|param| param.ident.clone())
+            .collect()
+    }
+
+    fn arg_names(&self) -> Vec<String> {
+        self.method
+            .args
+            .iter()
+            .map(|arg| arg.name.to_string())
+            .collect()
+    }
+
+    /// The type parameters of the method, to call a helper function from the
+    /// trait implementation
+    pub fn turbofish(&self) -> TokenStream {
+        if !self.is_generic() {
+            return quote! {};
+        }
+
+        let params = self.type_param_idents();
+        quote! { ::<#(#params),*> }
+    }
+
+    /// The type parameters of the helper functions of a generic method, whose
+    /// bounds are moved to the where clause
+    fn helper_generics(&self) -> TokenStream {
+        if !self.is_generic() {
+            return quote! {};
+        }
+
+        let params = self.type_param_idents();
+        quote! { <#(#params),*> }
+    }
+
+    /// The bounds the mock of an instantiation needs, added to the bounds of
+    /// the method. Inspecting the calls also needs clonable inputs.
+    fn helper_where_clause(&self, inspection: bool) -> TokenStream {
+        if !self.is_generic() {
+            return quote! {};
+        }
+
+        let krate = &self.krate;
+        let predicates = self
+            .method
+            .signature
+            .generics
+            .where_clause
+            .iter()
+            .flat_map(|where_clause| where_clause.predicates.iter());
+        let params = self.type_params().into_iter().map(|param| {
+            let ident = &param.ident;
+            let bounds = param.bounds.iter();
+            quote! { #ident: #(#bounds +)* 'static }
+        });
+        let return_input_types = self.return_input_types();
+        let output_type = self.return_output_type();
+        let thread_safe = match self.local {
+            true => quote! {},
+            false => quote! { + ::core::marker::Send },
+        };
+        let clone = match inspection {
+            true => quote! { + ::core::clone::Clone },
+            false => quote! {},
+        };
+        // Indexed mocks hash the values matched by the arguments
+        let indexed = self.method.args.iter().filter(|_| self.indexed).map(|arg| {
+            let ty = match &arg.original_type {
+                Type::Reference(reference) => &*reference.elem,
+                ty => ty,
+            };
+            quote! { #ty: ::core::hash::Hash + ::core::cmp::Eq }
+        });
+
+        quote! {
+            where
+                #(#predicates,)*
+                #(#params,)*
+                #(#indexed,)*
+                #return_input_types: ::core::cmp::PartialEq + ::core::fmt::Debug + #krate::Arguments #thread_safe #clone + 'static,
+                #output_type: ::core::clone::Clone #thread_safe + 'static,
+        }
+    }
+
+    /// Create the accessor of the per-thread mock of a method without receiver
+    fn static_mock_fn(&self) -> TokenStream {
+        let mock_name = self.mock_name();
+        let fn_name = self.helper_fn_name("mock");
+        let mock_type = self.mock_type();
+        let registered = match (self.local, self.indexed) {
+            (true, true) => quote! { per_thread_indexed },
+            (true, false) => quote! { per_thread },
+            (false, true) => quote! { registered_indexed },
+            (false, false) => quote! { registered },
+        };
+        let return_input_types = self.return_input_types();
+        let output_type = self.return_output_type();
+        let arg_names = self.arg_names();
+
+        quote! {
+            pub fn #fn_name() -> #mock_type<#return_input_types, #output_type> {
+                #mock_type::#registered(::core::module_path!(), ::std::string::String::from(#mock_name))
+                    .with_arg_names(&[#(#arg_names),*])
+            }
+        }
+    }
+
+    fn prefixed_name(&self, prefix: &str) -> Ident {
+        let name = self.name();
+        Ident::new(&format!("{}_{}", prefix, name), name.span())
     }
 
     pub fn args(&self) -> Vec<Argument> {
@@ -117,58 +396,151 @@ impl MockFn {
     }
 
     fn when_fn(&self) -> TokenStream {
-        let name = self.name();
+        let receiver = self.helper_receiver();
+        let generics = self.helper_generics();
+        let where_clause = self.helper_where_clause(false);
+        let mock = self.mock_expr();
         let fn_name = self.when_fn_name();
         let args = self.args_input_types();
         let args_input_mapping = self.args_input_mapping();
         let return_input_types = self.return_input_types();
         let return_input_names = self.return_input_names();
         let output_type = self.return_output_type();
+        let when_type = self.when_type();
 
         let quote = quote! {
-            pub fn #fn_name(&self, #(#args),*) -> mock_it::When<#return_input_types, #output_type> {
+            pub fn #fn_name #generics(#receiver #(#args),*) -> #when_type<#return_input_types, #output_type> #where_clause {
                 #(#args_input_mapping);*
-                self.#name.when((#(#return_input_names),*))
+                #mock.when((#(#return_input_names),*))
             }
         };
         quote.into()
     }
 
     fn called_fn(&self) -> TokenStream {
-        let name = self.name();
+        let receiver = self.helper_receiver();
+        let generics = self.helper_generics();
+        let where_clause = self.helper_where_clause(false);
         let fn_name = self.called_fn_name();
         let args = self.args_input_types();
         let args_input_mapping = self.args_input_mapping();
         let return_input_names = self.return_input_names();
-        let output_type = self.return_output_type();
+        let (output_type, call) = self.call(quote! { (#(#return_input_names),*) });
 
         let quote = quote! {
-            pub fn #fn_name(&self, #(#args),*) -> #output_type {
+            #[track_caller]
+            pub fn #fn_name #generics(#receiver #(#args),*) -> #output_type #where_clause {
                 #(#args_input_mapping);*
-                self.#name.called((#(#return_input_names),*))
+                #call
             }
         };
         quote.into()
     }
 
+    /// The output type of the method, with the call of its mock returning it.
+    /// Borrowed outputs are kept by the mock so the method can return
+    /// references to them.
+    fn call(&self, input: TokenStream) -> (TokenStream, TokenStream) {
+        let mock = self.mock_expr();
+        match &self.method.borrowed {
+            Some(Borrowed::Reference(ty)) => (
+                quote! { &#ty },
+                quote! { ::core::ops::Deref::deref(#mock.called_ref(#input)) },
+            ),
+            Some(Borrowed::Option(ty)) => (
+                quote! { ::core::option::Option<&#ty> },
+                quote! { #mock.called_ref(#input).as_deref() },
+            ),
+            None => (self.return_output_type(), quote! { #mock.called(#input) }),
+        }
+    }
+
     fn was_called_with_fn(&self) -> TokenStream {
-        let name = self.name();
+        let receiver = self.helper_receiver();
+        let generics = self.helper_generics();
+        let where_clause = self.helper_where_clause(false);
+        let mock = self.mock_expr();
         let fn_name = self.was_called_with_fn_name();
         let args = self.args_input_types();
         let args_input_mapping = self.args_input_mapping();
         let return_input_types = self.return_input_types();
         let return_input_names = self.return_input_names();
+        let output_type = self.return_output_type();
+        let validator_type = self.validator_type();
 
         let quote = quote! {
-            pub fn #fn_name(&self, #(#args),*) -> mock_it::Validator<#return_input_types> {
+            pub fn #fn_name #generics(#receiver #(#args),*) -> #validator_type<#return_input_types, #output_type> #where_clause {
                 #(#args_input_mapping);*
-                self.#name.was_called_with((#(#return_input_names),*))
+                #mock.was_called_with((#(#return_input_names),*))
             }
         };
         quote.into()
     }
 
+    fn inspection_fns(&self) -> TokenStream {
+        let krate = &self.krate;
+        let mock = self.mock_expr();
+        let generics = self.helper_generics();
+        let where_clause = self.helper_where_clause(true);
+        let calls_fn_name = self.prefixed_name("calls");
+        let call_count_fn_name = self.prefixed_name("call_count");
+        let last_call_fn_name = self.prefixed_name("last_call");
+        let nth_call_fn_name = self.prefixed_name("nth_call");
+        let call_records_fn_name = self.prefixed_name("call_records");
+        let return_input_types = self.return_input_types();
+        let output_type = self.return_output_type();
+
+        quote! {
+            pub fn #calls_fn_name #generics(&self) -> ::std::vec::Vec<#return_input_types> #where_clause {
+                #mock.calls()
+            }
+
+            pub fn #call_count_fn_name #generics(&self) -> usize #where_clause {
+                #mock.call_count()
+            }
+
+            pub fn #last_call_fn_name #generics(&self) -> ::core::option::Option<#return_input_types> #where_clause {
+                #mock.last_call()
+            }
+
+            pub fn #nth_call_fn_name #generics(&self, index: usize) -> ::core::option::Option<#return_input_types> #where_clause {
+                #mock.nth_call(index)
+            }
+
+            pub fn #call_records_fn_name #generics(&self) -> ::std::vec::Vec<#krate::Call<#return_input_types, #output_type>> #where_clause {
+                #mock.call_records()
+            }
+        }
+    }
+
+    /// Subscriptions need a thread-safe mock, so local mocks don't get them
+    fn subscription_fns(&self) -> TokenStream {
+        if self.local {
+            return quote! {};
+        }
+
+        let krate = &self.krate;
+        let mock = self.mock_expr();
+        let generics = self.helper_generics();
+        let where_clause = self.helper_where_clause(true);
+        let subscribe_fn_name = self.prefixed_name("subscribe");
+        let next_call_fn_name = self.prefixed_name("next_call");
+        let return_input_types = self.return_input_types();
+        let output_type = self.return_output_type();
+
+        quote! {
+            pub fn #subscribe_fn_name #generics(&self) -> #krate::Subscription<#return_input_types, #output_type> #where_clause {
+                #mock.subscribe()
+            }
+
+            pub async fn #next_call_fn_name #generics(&self) -> #return_input_types #where_clause {
+                #mock.next_call().await
+            }
+        }
+    }
+
     fn args_input_types(&self) -> Vec<TokenStream> {
+        let krate = &self.krate;
         self.method
             .args
             .iter()
@@ -176,13 +548,14 @@ impl MockFn {
                 let ty = &arg.original_type;
                 let name = &arg.name;
                 quote! {
-                    #name: mock_it::Matcher<#ty>
+                    #name: #krate::Matcher<#ty>
                 }
             })
             .collect()
     }
 
     fn args_input_mapping(&self) -> Vec<TokenStream> {
+        let krate = &self.krate;
         self.method
             .args
             .iter()
@@ -193,8 +566,8 @@ impl MockFn {
                 }
                 quote! {
                     let #name  = match #name {
-                        mock_it::Matcher::Val(val) => mock_it::Matcher::Val(std::sync::Arc::from(val.clone())),
-                        mock_it::Matcher::Any => mock_it::Matcher::Any,
+                        #krate::Matcher::Val(val) => #krate::Matcher::Val(::std::sync::Arc::from(val.clone())),
+                        #krate::Matcher::Any => #krate::Matcher::Any,
                     };
                 }
             })
@@ -1,18 +1,35 @@
 use proc_macro2::TokenStream;
 use quote::quote;
+use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
 use syn::{
-    FnArg, Generics, Ident, ItemTrait, Pat, PatType, ReturnType, Signature, TraitItem,
-    TraitItemMethod, Type,
+    parse_quote, FnArg, GenericArgument, Generics, Ident, ItemTrait, Lifetime, Pat, PatType, Path,
+    PathArguments, Receiver, ReturnType, Signature, TraitItem, TraitItemMethod, Type,
 };
 
 #[derive(Clone)]
 pub struct TraitMethodType {
     pub args: Vec<Argument>,
+    /// Whether the method takes `self` in any form
+    pub has_receiver: bool,
+    /// The output the mock keeps, which is shared for borrowed outputs
     pub return_type: Option<Type>,
+    /// How the method borrows its output from the mock, if it returns a
+    /// reference tied to the receiver
+    pub borrowed: Option<Borrowed>,
     pub signature: Signature,
     pub generics: Generics,
 }
 
+/// A reference returned by a method, with the type it points to
+#[derive(Clone)]
+pub enum Borrowed {
+    /// `&T`, kept as `Arc<T>`
+    Reference(Type),
+    /// `Option<&T>`, kept as `Option<Arc<T>>`
+    Option(Type),
+}
+
 #[derive(Clone)]
 pub struct Argument {
     pub is_reference: bool,
@@ -21,11 +38,14 @@ pub struct Argument {
     pub original_type: Type,
 }
 
-pub fn get_trait_method_types(item_trait: &ItemTrait) -> Vec<TraitMethodType> {
+pub fn get_trait_method_types(
+    item_trait: &ItemTrait,
+    krate: &Path,
+) -> syn::Result<Vec<TraitMethodType>> {
     let generics = &item_trait.generics;
 
     get_trait_methods(item_trait)
-        .map(|method| get_method_types(method, generics))
+        .map(|method| get_method_types(method, generics, krate))
         .collect()
 }
 
@@ -39,60 +59,219 @@ fn get_trait_methods(item_trait: &ItemTrait) -> impl Iterator<Item = &TraitItemM
     })
 }
 
-fn get_method_types(method: &TraitItemMethod, generics: &Generics) -> TraitMethodType {
-    let args: Vec<Argument> = method
+fn get_method_types(
+    method: &TraitItemMethod,
+    generics: &Generics,
+    krate: &Path,
+) -> syn::Result<TraitMethodType> {
+    let mut static_lifetimes = StaticLifetimes::new(&method.sig.generics);
+    let args = method
         .sig
         .inputs
         .iter()
         .filter_map(|arg| match arg {
-            FnArg::Typed(inner) => Some(inner.clone()),
+            FnArg::Typed(inner) if !is_typed_receiver(inner) => Some(inner.clone()),
             _ => None,
         })
         .map(|arg| {
-            let original_type = arg.ty.clone();
+            let mut original_type = arg.ty.clone();
+            static_lifetimes.elide_reference(&mut original_type);
+            if let Some(lifetime) = static_lifetimes.find(&original_type) {
+                return Err(syn::Error::new_spanned(
+                    lifetime,
+                    "mock_it can't keep arguments borrowing a lifetime of the method, except through a reference like `&'a T`",
+                ));
+            }
             if let Type::Reference(reference) = *original_type.clone() {
                 let ty = reference.elem.clone();
                 let definition = quote! {
-                    mock_it::Matcher<std::sync::Arc<#ty>>
+                    #krate::Matcher<::std::sync::Arc<#ty>>
                 };
-                let name = get_pat_type_name(&arg);
-                return Argument {
+                let name = get_pat_type_name(&arg)?;
+                Ok(Argument {
                     is_reference: true,
                     definition,
                     name,
                     original_type: *original_type.clone(),
-                };
+                })
             } else {
                 let definition = quote! {
-                    mock_it::Matcher<#original_type>
+                    #krate::Matcher<#original_type>
                 };
-                let name = get_pat_type_name(&arg);
-                return Argument {
+                let name = get_pat_type_name(&arg)?;
+                Ok(Argument {
                     is_reference: false,
                     definition,
                     name,
                     original_type: *original_type.clone(),
-                };
+                })
             }
         })
-        .collect();
-    let return_type = match method.sig.output {
-        ReturnType::Default => None,
-        ReturnType::Type(_, ref return_type) => Some(*return_type.clone()),
+        .collect::<syn::Result<Vec<Argument>>>()?;
+    let (return_type, borrowed) = match method.sig.output {
+        ReturnType::Default => (None, None),
+        ReturnType::Type(_, ref return_type) => {
+            let (mut return_type, mut borrowed) = match borrowed_output(&method.sig, return_type) {
+                Some((output, borrowed)) => (output, Some(borrowed)),
+                None => (*return_type.clone(), None),
+            };
+            static_lifetimes.visit_type_mut(&mut return_type);
+            if let Some(Borrowed::Reference(ty) | Borrowed::Option(ty)) = &mut borrowed {
+                static_lifetimes.visit_type_mut(ty);
+            }
+            (Some(return_type), borrowed)
+        }
     };
     let signature = method.sig.clone();
+    if signature.receiver().is_none() && signature.generics.type_params().next().is_some() {
+        return Err(syn::Error::new_spanned(
+            &signature.generics,
+            "mock_it doesn't support type parameters on functions without a receiver",
+        ));
+    }
 
-    TraitMethodType {
+    if borrowed.is_some() && signature.generics.type_params().next().is_some() {
+        return Err(syn::Error::new_spanned(
+            &signature.output,
+            "mock_it doesn't support references returned by methods with type parameters",
+        ));
+    }
+
+    Ok(TraitMethodType {
+        has_receiver: signature.receiver().is_some(),
         signature,
         args,
         return_type,
+        borrowed,
         generics: generics.clone(),
+    })
+}
+
+/// The output the mock keeps for a method returning a reference borrowed from
+/// its receiver, like `&str` or `Option<&T>`. Other references are kept as
+/// they are, so they must be configured with `'static` values.
+fn borrowed_output(signature: &Signature, return_type: &Type) -> Option<(Type, Borrowed)> {
+    let receiver_lifetime = match signature.receiver() {
+        Some(FnArg::Receiver(Receiver {
+            reference: Some((_and, lifetime)),
+            ..
+        })) => lifetime.as_ref(),
+        _ => return None,
+    };
+    let borrowed_elem = |ty: &Type| match ty {
+        Type::Reference(reference)
+            if reference.mutability.is_none()
+                && (reference.lifetime.is_none()
+                    || reference.lifetime.as_ref() == receiver_lifetime) =>
+        {
+            Some(*reference.elem.clone())
+        }
+        _ => None,
+    };
+
+    if let Some(elem) = borrowed_elem(return_type) {
+        let output = parse_quote! { ::std::sync::Arc<#elem> };
+        return Some((output, Borrowed::Reference(elem)));
+    }
+
+    let option_arg = match return_type {
+        Type::Path(path) if path.qself.is_none() => {
+            let segment = path.path.segments.last()?;
+            match &segment.arguments {
+                PathArguments::AngleBracketed(arguments)
+                    if segment.ident == "Option" && arguments.args.len() == 1 =>
+                {
+                    match &arguments.args[0] {
+                        GenericArgument::Type(ty) => borrowed_elem(ty),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    let elem = option_arg?;
+    let output = parse_quote! { ::core::option::Option<::std::sync::Arc<#elem>> };
+    Some((output, Borrowed::Option(elem)))
+}
+
+/// Whether the argument is a receiver with a type, like `self: Box<Self>`,
+/// which isn't an input of the mock
+fn is_typed_receiver(pat_type: &PatType) -> bool {
+    match &*pat_type.pat {
+        Pat::Ident(inner) => inner.ident == "self",
+        _ => false,
     }
 }
 
-fn get_pat_type_name(pat_type: &PatType) -> Ident {
+fn get_pat_type_name(pat_type: &PatType) -> syn::Result<Ident> {
     match &*pat_type.pat {
-        Pat::Ident(inner) => inner.ident.clone(),
-        _ => panic!("unknown argument pattern"),
+        Pat::Ident(inner) => Ok(inner.ident.clone()),
+        pat => Err(syn::Error::new_spanned(
+            pat,
+            "mock_it only supports arguments bound to a name, like `arg: Type`",
+        )),
+    }
+}
+
+/// Replace the lifetimes of a method by `'static` in the types the mock
+/// stores, which can't borrow from a single call
+struct StaticLifetimes(Vec<Lifetime>);
+
+impl StaticLifetimes {
+    fn new(generics: &Generics) -> Self {
+        Self(
+            generics
+                .lifetimes()
+                .map(|param| param.lifetime.clone())
+                .collect(),
+        )
+    }
+
+    /// Elide the method lifetime of a reference argument, which the mock keeps
+    /// behind an `Arc` instead
+    fn elide_reference(&self, ty: &mut Type) {
+        if let Type::Reference(reference) = ty {
+            let is_method_lifetime = match &reference.lifetime {
+                Some(lifetime) => self.0.contains(lifetime),
+                None => false,
+            };
+            if is_method_lifetime {
+                reference.lifetime = None;
+            }
+        }
+    }
+
+    /// The first lifetime of the method used by the type, if any
+    fn find(&self, ty: &Type) -> Option<Lifetime> {
+        let mut finder = FindLifetime {
+            lifetimes: &self.0,
+            found: None,
+        };
+        finder.visit_type(ty);
+        finder.found
+    }
+}
+
+/// Looks for a use of one of the lifetimes
+struct FindLifetime<'a> {
+    lifetimes: &'a [Lifetime],
+    found: Option<Lifetime>,
+}
+
+impl<'ast> Visit<'ast> for FindLifetime<'_> {
+    fn visit_lifetime(&mut self, lifetime: &'ast Lifetime) {
+        if self.found.is_none() && self.lifetimes.contains(lifetime) {
+            self.found = Some(lifetime.clone());
+        }
+    }
+}
+
+impl VisitMut for StaticLifetimes {
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if self.0.contains(lifetime) {
+            *lifetime = Lifetime::new("'static", lifetime.span());
+        }
     }
 }
@@ -0,0 +1,306 @@
+use crate::matcher::Matcher;
+use crate::rule::Rule;
+use crate::storage::Storage;
+use std::fmt::Debug;
+
+/// Inputs a mock can compare argument by argument, to point at the arguments
+/// of an unexpected call which didn't match the closest rule.
+pub trait Arguments {
+    /// Debug representation of each argument
+    fn arguments(&self) -> Vec<String>;
+
+    /// Whether each argument matches the argument at the same position of the
+    /// other input
+    fn matching(&self, other: &Self) -> Vec<bool>;
+}
+
+impl<T: PartialEq + Debug> Arguments for Matcher<T> {
+    fn arguments(&self) -> Vec<String> {
+        vec![format!("{:?}", self)]
+    }
+
+    fn matching(&self, other: &Self) -> Vec<bool> {
+        vec![self == other]
+    }
+}
+
+macro_rules! single_argument {
+    ($($ty:ty),*) => {
+        $(
+            impl Arguments for $ty {
+                fn arguments(&self) -> Vec<String> {
+                    vec![format!("{:?}", self)]
+                }
+
+                fn matching(&self, other: &Self) -> Vec<bool> {
+                    vec![self == other]
+                }
+            }
+        )*
+    };
+}
+
+single_argument!(
+    bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, String,
+    &str
+);
+
+impl Arguments for () {
+    fn arguments(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn matching(&self, _other: &Self) -> Vec<bool> {
+        Vec::new()
+    }
+}
+
+macro_rules! arguments_tuple {
+    ($(($name:ident, $other:ident)),+) => {
+        impl<$($name: PartialEq + Debug),+> Arguments for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn arguments(&self) -> Vec<String> {
+                let ($($name,)+) = self;
+                vec![$(format!("{:?}", $name)),+]
+            }
+
+            #[allow(non_snake_case)]
+            fn matching(&self, other: &Self) -> Vec<bool> {
+                let ($($name,)+) = self;
+                let ($($other,)+) = other;
+                vec![$($name == $other),+]
+            }
+        }
+    };
+}
+
+arguments_tuple!((A, A2));
+arguments_tuple!((A, A2), (B, B2));
+arguments_tuple!((A, A2), (B, B2), (C, C2));
+arguments_tuple!((A, A2), (B, B2), (C, C2), (D, D2));
+arguments_tuple!((A, A2), (B, B2), (C, C2), (D, D2), (E, E2));
+arguments_tuple!((A, A2), (B, B2), (C, C2), (D, D2), (E, E2), (F, F2));
+arguments_tuple!(
+    (A, A2),
+    (B, B2),
+    (C, C2),
+    (D, D2),
+    (E, E2),
+    (F, F2),
+    (G, G2)
+);
+arguments_tuple!(
+    (A, A2),
+    (B, B2),
+    (C, C2),
+    (D, D2),
+    (E, E2),
+    (F, F2),
+    (G, G2),
+    (H, H2)
+);
+arguments_tuple!(
+    (A, A2),
+    (B, B2),
+    (C, C2),
+    (D, D2),
+    (E, E2),
+    (F, F2),
+    (G, G2),
+    (H, H2),
+    (I, I2)
+);
+arguments_tuple!(
+    (A, A2),
+    (B, B2),
+    (C, C2),
+    (D, D2),
+    (E, E2),
+    (F, F2),
+    (G, G2),
+    (H, H2),
+    (I, I2),
+    (J, J2)
+);
+arguments_tuple!(
+    (A, A2),
+    (B, B2),
+    (C, C2),
+    (D, D2),
+    (E, E2),
+    (F, F2),
+    (G, G2),
+    (H, H2),
+    (I, I2),
+    (J, J2),
+    (K, K2)
+);
+arguments_tuple!(
+    (A, A2),
+    (B, B2),
+    (C, C2),
+    (D, D2),
+    (E, E2),
+    (F, F2),
+    (G, G2),
+    (H, H2),
+    (I, I2),
+    (J, J2),
+    (K, K2),
+    (L, L2)
+);
+
+/// Names of the arguments of a mock, with the functions comparing its inputs.
+/// They are kept as functions so mocks don't require `Arguments` inputs.
+#[derive(Debug)]
+pub(crate) struct ArgumentNames<I> {
+    names: Vec<String>,
+    arguments: fn(&I) -> Vec<String>,
+    matching: fn(&I, &I) -> Vec<bool>,
+}
+
+impl<I: Arguments> ArgumentNames<I> {
+    pub(crate) fn new(names: &[&str]) -> ArgumentNames<I> {
+        ArgumentNames {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            arguments: I::arguments,
+            matching: I::matching,
+        }
+    }
+}
+
+impl<I> ArgumentNames<I> {
+    fn name(&self, position: usize) -> String {
+        match self.names.get(position) {
+            Some(name) => name.clone(),
+            None => format!("#{}", position),
+        }
+    }
+
+    /// The input of the first rule matching the most arguments of the input,
+    /// with whether each argument matches
+    fn closest_match<'a, S: Storage, O>(
+        &self,
+        rules: &'a [Rule<S, I, O>],
+        input: &I,
+    ) -> Option<(&'a I, Vec<bool>)> {
+        let mut closest: Option<(&I, Vec<bool>)> = None;
+        for rule in rules {
+            let matching = (self.matching)(&rule.input, input);
+            let matched = |matching: &[bool]| matching.iter().filter(|matches| **matches).count();
+            match &closest {
+                Some((_rule, best)) if matched(best) >= matched(&matching) => {}
+                _ => closest = Some((&rule.input, matching)),
+            }
+        }
+
+        closest
+    }
+
+    /// Compare the input with the rule, argument by argument
+    fn diff(&self, rule: &I, input: &I, matching: &[bool]) -> String {
+        let expected = (self.arguments)(rule);
+        let actual = (self.arguments)(input);
+        let lines: Vec<String> = matching
+            .iter()
+            .enumerate()
+            .map(|(position, matches)| match matches {
+                true => format!("    {}: {}", self.name(position), actual[position]),
+                false => format!(
+                    "    {}: expected {}, got {} <- mismatch",
+                    self.name(position),
+                    expected[position],
+                    actual[position]
+                ),
+            })
+            .collect();
+
+        lines.join("\n")
+    }
+}
+
+/// The failure message of a mock called with an input no rule matches
+pub(crate) fn unexpected_input_message<S: Storage, I: Debug, O>(
+    name: &str,
+    input: &I,
+    rules: &[Rule<S, I, O>],
+    arguments: Option<&ArgumentNames<I>>,
+) -> String {
+    let mut message = format!(
+        "Mock \"{}\" called with unexpected input: {:?}, did you forget to configure your mock ?",
+        name,
+        format!("{:?}", input)
+    );
+
+    if rules.is_empty() {
+        message.push_str("\nNo rule is configured");
+        return message;
+    }
+
+    message.push_str("\nConfigured rules:");
+    for rule in rules {
+        message.push_str(&format!("\n    {:?}", rule.input));
+    }
+
+    if let Some(arguments) = arguments {
+        if let Some((rule, matching)) = arguments.closest_match(rules, input) {
+            message.push_str(&format!(
+                "\nClosest rule: {:?}\n{}",
+                rule,
+                arguments.diff(rule, input, &matching)
+            ));
+        }
+    }
+
+    message
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matcher::{any, eq};
+    use crate::storage::Local;
+
+    #[test]
+    fn arguments() {
+        assert_eq!((eq(1), any::<&str>()).arguments(), vec!["1", "Any"]);
+        assert_eq!(
+            (eq(1), eq("a")).matching(&(eq(1), eq("b"))),
+            vec![true, false]
+        );
+        assert_eq!(().arguments(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn message_shows_the_closest_rule() {
+        let names = ArgumentNames::new(&["id", "name"]);
+        let rules: Vec<Rule<Local, _, _>> = vec![
+            Rule::new((eq(1), eq("a")), ()),
+            Rule::new((eq(2), eq("b")), ()),
+            Rule::new((eq(2), eq("c")), ()),
+        ];
+
+        let message = unexpected_input_message("A.b", &(eq(2), eq("d")), &rules, Some(&names));
+
+        assert_eq!(
+            message,
+            "Mock \"A.b\" called with unexpected input: \"(2, \\\"d\\\")\", did you forget to configure your mock ?
+Configured rules:
+    (1, \"a\")
+    (2, \"b\")
+    (2, \"c\")
+Closest rule: (2, \"b\")
+    id: 2
+    name: expected \"b\", got \"d\" <- mismatch"
+        );
+    }
+
+    #[test]
+    fn message_without_rules() {
+        let rules: Vec<Rule<Local, i32, ()>> = Vec::new();
+
+        let message = unexpected_input_message("A.b", &1, &rules, None);
+
+        assert!(message.ends_with("your mock ?\nNo rule is configured"));
+    }
+}
@@ -0,0 +1,399 @@
+use crate::key::{key, Key};
+use crate::sync::Reentrant;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::panic::Location;
+use std::sync::Arc;
+use std::thread::ThreadId;
+use std::time::Instant;
+
+/// Calls shared between the clones of a mock
+pub(crate) type Calls<I, O> = Arc<Reentrant<CallLog<I, O>>>;
+
+/// A call recorded by a mock
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call<I, O = ()> {
+    pub input: I,
+    pub outcome: Outcome<O>,
+    /// When the call was made
+    pub at: Instant,
+    pub thread: ThreadId,
+    pub thread_name: Option<String>,
+    /// Where the call was made from, as tracked by `#[track_caller]`
+    pub location: &'static Location<'static>,
+    pub(crate) verified: bool,
+}
+
+/// How a call to a mock ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome<O> {
+    /// The mock returned the output of the rule at the position, in
+    /// configuration order
+    Returned { output: O, rule: usize },
+    /// The mock panicked with the message
+    Panicked(String),
+}
+
+impl<I, O> Call<I, O> {
+    /// Record a call made now, from the current thread and the caller location
+    #[track_caller]
+    pub fn new(input: I, outcome: Outcome<O>) -> Call<I, O> {
+        let thread = std::thread::current();
+        Call {
+            input,
+            outcome,
+            at: Instant::now(),
+            thread: thread.id(),
+            thread_name: thread.name().map(String::from),
+            location: Location::caller(),
+            verified: false,
+        }
+    }
+
+    /// Whether the call was matched by a verified expectation
+    pub fn is_verified(&self) -> bool {
+        self.verified
+    }
+
+    /// The value returned by the mock, if it didn't panic
+    pub fn output(&self) -> Option<&O> {
+        match &self.outcome {
+            Outcome::Returned { output, .. } => Some(output),
+            Outcome::Panicked(_) => None,
+        }
+    }
+
+    /// Position of the rule which answered the call, if any
+    pub fn rule(&self) -> Option<usize> {
+        match &self.outcome {
+            Outcome::Returned { rule, .. } => Some(*rule),
+            Outcome::Panicked(_) => None,
+        }
+    }
+
+    pub fn panicked(&self) -> bool {
+        matches!(self.outcome, Outcome::Panicked(_))
+    }
+
+    /// Where and from which thread the call was made, for failure messages
+    pub(crate) fn origin(&self) -> String {
+        match &self.thread_name {
+            Some(name) => format!("at {} on thread '{}'", self.location, name),
+            None => format!("at {} on {:?}", self.location, self.thread),
+        }
+    }
+}
+
+impl<I: std::fmt::Debug, O> Call<I, O> {
+    /// The input of the call with its origin, for failure messages
+    pub(crate) fn describe(&self) -> String {
+        format!("{:?} (called {})", self.input, self.origin())
+    }
+}
+
+/// How much call history a mock keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum History {
+    /// Keep every call
+    #[default]
+    Unbounded,
+    /// Keep the last calls, discarding the oldest ones
+    Last(usize),
+    /// Only count the calls
+    CountOnly,
+}
+
+/// The calls recorded by a mock, in call order
+#[derive(Debug)]
+pub(crate) struct CallLog<I, O = ()> {
+    calls: VecDeque<Call<I, O>>,
+    discarded: usize,
+    history: History,
+    index: Option<CallIndex<I>>,
+    /// Formats the inputs, once a call was recorded by a mock, so failures
+    /// about discarded calls can tell which input was asked about
+    describe: Option<fn(&I) -> String>,
+}
+
+/// The kept calls of an indexed mock by the hash of their input, so an
+/// expectation only compares its input with the calls which may match it.
+/// Calls are numbered like `CallLog::get`, counting the discarded ones.
+#[derive(Debug)]
+struct CallIndex<I> {
+    key: fn(&I) -> Option<u64>,
+    keys: VecDeque<Option<u64>>,
+    exact: HashMap<u64, VecDeque<usize>>,
+    inexact: VecDeque<usize>,
+}
+
+impl<I, O> CallLog<I, O> {
+    pub(crate) fn new(history: History) -> CallLog<I, O> {
+        CallLog {
+            calls: VecDeque::new(),
+            discarded: 0,
+            history,
+            index: None,
+            describe: None,
+        }
+    }
+
+    /// Change the history policy, handing out the calls it discards to be
+    /// dropped once the log is unlocked
+    pub(crate) fn set_history(&mut self, history: History) -> Vec<Call<I, O>> {
+        self.history = history;
+        std::iter::from_fn(|| self.discard()).collect()
+    }
+
+    /// Number of calls recorded, including the discarded ones
+    pub(crate) fn len(&self) -> usize {
+        self.discarded + self.calls.len()
+    }
+
+    /// Number of calls discarded by the history policy
+    pub(crate) fn discarded(&self) -> usize {
+        self.discarded
+    }
+
+    /// The call at the index, counting the discarded calls, if it was kept
+    pub(crate) fn get(&self, index: usize) -> Option<&Call<I, O>> {
+        index
+            .checked_sub(self.discarded)
+            .and_then(|index| self.calls.get(index))
+    }
+
+    /// The last call, if it was kept
+    pub(crate) fn last(&self) -> Option<&Call<I, O>> {
+        self.calls.back()
+    }
+
+    /// The function computing the index key of an input, if the log is
+    /// indexed. It is handed out so user `Hash` code runs without the lock.
+    pub(crate) fn key_fn(&self) -> Option<fn(&I) -> Option<u64>> {
+        self.index.as_ref().map(|index| index.key)
+    }
+
+    /// The input formatted for a failure, once a mock recorded a call
+    pub(crate) fn describe(&self, input: &I) -> Option<String> {
+        self.describe.map(|describe| describe(input))
+    }
+
+    /// The calls kept by the history policy
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Call<I, O>> {
+        self.calls.iter()
+    }
+
+    /// Discard the oldest call when the history policy keeps fewer calls
+    fn discard(&mut self) -> Option<Call<I, O>> {
+        let kept = match self.history {
+            History::Unbounded => return None,
+            History::Last(kept) => kept,
+            History::CountOnly => 0,
+        };
+        if self.calls.len() <= kept {
+            return None;
+        }
+
+        let call = self.calls.pop_front();
+        if let Some(index) = &mut self.index {
+            let key = index.keys.pop_front().flatten();
+            index.numbers(key).pop_front();
+            if let Some(key) = key.filter(|key| index.exact[key].is_empty()) {
+                index.exact.remove(&key);
+            }
+        }
+        self.discarded += 1;
+        call
+    }
+}
+
+impl<I: Debug, O> CallLog<I, O> {
+    /// Record a call made to a mock, whose input has the key in an indexed
+    /// log. A call without key is compared with every expectation. The call
+    /// discarded by the history policy, if any, is handed out to be dropped
+    /// once the log is unlocked.
+    pub(crate) fn record(&mut self, call: Call<I, O>, key: Option<u64>) -> Option<Call<I, O>> {
+        self.describe = Some(|input| format!("{:?}", input));
+        let number = self.len();
+        if let Some(index) = &mut self.index {
+            index.keys.push_back(key);
+            index.numbers(key).push_back(number);
+        }
+
+        self.calls.push_back(call);
+        self.discard()
+    }
+}
+
+impl<I: Key, O> CallLog<I, O> {
+    pub(crate) fn indexed(history: History) -> CallLog<I, O> {
+        CallLog {
+            index: Some(CallIndex {
+                key: key::<I>,
+                keys: VecDeque::new(),
+                exact: HashMap::new(),
+                inexact: VecDeque::new(),
+            }),
+            ..CallLog::new(history)
+        }
+    }
+}
+
+impl<I: PartialEq, O> CallLog<I, O> {
+    /// Positions among the kept calls of the calls matching the input with
+    /// the key, in call order
+    pub(crate) fn matching(&self, input: &I, key: Option<u64>) -> Vec<usize> {
+        let matches = |position: &usize| self.calls[*position].input == *input;
+        match (&self.index, key) {
+            (Some(index), Some(key)) => {
+                let mut positions: Vec<usize> = index
+                    .candidates(key)
+                    .map(|number| number - self.discarded)
+                    .filter(matches)
+                    .collect();
+                positions.sort_unstable();
+                positions
+            }
+            _ => (0..self.calls.len()).filter(matches).collect(),
+        }
+    }
+
+    /// Mark the kept calls at the positions as verified
+    pub(crate) fn mark_verified(&mut self, positions: &[usize]) {
+        for position in positions {
+            self.calls[*position].verified = true;
+        }
+    }
+}
+
+impl<I> CallIndex<I> {
+    /// Numbers of the calls which may match an input with the key
+    fn candidates(&self, key: u64) -> impl Iterator<Item = usize> + '_ {
+        let exact = self.exact.get(&key).into_iter().flatten();
+        exact.chain(&self.inexact).copied()
+    }
+
+    fn numbers(&mut self, key: Option<u64>) -> &mut VecDeque<usize> {
+        match key {
+            Some(key) => self.exact.entry(key).or_default(),
+            None => &mut self.inexact,
+        }
+    }
+}
+
+impl<I, O> Default for CallLog<I, O> {
+    fn default() -> CallLog<I, O> {
+        CallLog::new(History::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matcher::{eq, Matcher};
+
+    #[track_caller]
+    fn call(input: i32) -> Call<i32, ()> {
+        Call::new(
+            input,
+            Outcome::Returned {
+                output: (),
+                rule: 0,
+            },
+        )
+    }
+
+    fn inputs(log: &CallLog<i32, ()>) -> Vec<i32> {
+        log.iter().map(|call| call.input).collect()
+    }
+
+    #[test]
+    fn unbounded_keeps_every_call() {
+        let mut log = CallLog::new(History::Unbounded);
+        (0..5).for_each(|input| drop(log.record(call(input), None)));
+
+        assert_eq!(inputs(&log), vec![0, 1, 2, 3, 4]);
+        assert_eq!(log.len(), 5);
+        assert_eq!(log.discarded(), 0);
+    }
+
+    #[test]
+    fn last_keeps_the_most_recent_calls() {
+        let mut log = CallLog::new(History::Last(2));
+        (0..5).for_each(|input| drop(log.record(call(input), None)));
+
+        assert_eq!(inputs(&log), vec![3, 4]);
+        assert_eq!(log.len(), 5);
+        assert_eq!(log.discarded(), 3);
+        assert_eq!(log.get(2), None);
+        assert_eq!(log.get(3).map(|call| call.input), Some(3));
+    }
+
+    #[test]
+    fn count_only_keeps_no_call() {
+        let mut log = CallLog::new(History::CountOnly);
+        (0..5).for_each(|input| drop(log.record(call(input), None)));
+
+        assert_eq!(inputs(&log), Vec::<i32>::new());
+        assert_eq!(log.len(), 5);
+        assert_eq!(log.discarded(), 5);
+    }
+
+    #[test]
+    fn indexed_log_matches_kept_calls() {
+        let matched = |input| {
+            Call::new(
+                eq(input),
+                Outcome::Returned {
+                    output: (),
+                    rule: 0,
+                },
+            )
+        };
+        let mut log = CallLog::indexed(History::Last(3));
+        for input in [1, 2, 1, 3, 1] {
+            log.record(matched(input), key(&eq(input)));
+        }
+        log.record(matched(2), None);
+
+        let kept: Vec<Matcher<i32>> = log.iter().map(|call| call.input.clone()).collect();
+        assert_eq!(kept, vec![eq(3), eq(1), eq(2)]);
+        assert_eq!(log.matching(&eq(1), key(&eq(1))), vec![1]);
+        assert_eq!(log.matching(&eq(2), key(&eq(2))), vec![2]);
+        assert_eq!(log.matching(&eq(4), key(&eq(4))), Vec::<usize>::new());
+        assert_eq!(log.matching(&eq(3), None), vec![0]);
+    }
+
+    #[test]
+    fn outcome() {
+        let returned: Call<i32, &str> = Call::new(
+            1,
+            Outcome::Returned {
+                output: "one",
+                rule: 2,
+            },
+        );
+        let panicked: Call<i32, &str> = Call::new(2, Outcome::Panicked("unexpected".to_string()));
+
+        assert_eq!(returned.output(), Some(&"one"));
+        assert_eq!(returned.rule(), Some(2));
+        assert!(!returned.panicked());
+        assert_eq!(panicked.output(), None);
+        assert_eq!(panicked.rule(), None);
+        assert!(panicked.panicked());
+    }
+
+    #[test]
+    fn metadata() {
+        let before = Instant::now();
+        let call = call(1);
+        let line = line!() - 1;
+
+        assert!(call.at >= before);
+        assert_eq!(call.thread, std::thread::current().id());
+        assert_eq!(call.location.file(), file!());
+        assert_eq!(call.location.line(), line);
+        assert!(call
+            .describe()
+            .starts_with(&format!("1 (called at {}:{}", file!(), line)));
+    }
+}
@@ -0,0 +1,29 @@
+/// Create a closure forwarding its arguments to a mock, as a tuple for
+/// several arguments.
+///
+/// ```
+/// use mock_it::{mock_fn, Mock};
+///
+/// let mock: Mock<(i32, i32), i32> = Mock::new("add".to_string());
+/// mock.when((1, 2)).will_return(3);
+///
+/// let add = mock_fn!(mock, |a: i32, b: i32|);
+///
+/// assert_eq!(add(1, 2), 3);
+/// assert!(mock.was_called_with((1, 2)).called());
+/// ```
+#[macro_export]
+macro_rules! mock_fn {
+    ($mock:expr, ||) => {{
+        let mock = $mock.clone();
+        move || mock.called(())
+    }};
+    ($mock:expr, |$arg:ident $(: $ty:ty)?|) => {{
+        let mock = $mock.clone();
+        move |$arg $(: $ty)?| mock.called($arg)
+    }};
+    ($mock:expr, |$($arg:ident $(: $ty:ty)?),+|) => {{
+        let mock = $mock.clone();
+        move |$($arg $(: $ty)?),+| mock.called(($($arg),+))
+    }};
+}
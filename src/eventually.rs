@@ -0,0 +1,53 @@
+use crate::timer::{notify_at, Deadline};
+use crate::validator::Validator;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+/// Future resolving to the validator once its expectation holds or the
+/// timeout has passed. Pending futures are woken at their deadline by a
+/// single timer thread, shared by the whole process.
+pub struct Eventually<I, O = ()> {
+    validator: Option<Validator<I, O>>,
+    deadline: Instant,
+    /// Wakes the future at its deadline, until it is dropped
+    timer: Option<Deadline>,
+}
+
+impl<I, O> Eventually<I, O> {
+    pub(crate) fn new(validator: Validator<I, O>, deadline: Instant) -> Eventually<I, O> {
+        Eventually {
+            validator: Some(validator),
+            deadline,
+            timer: None,
+        }
+    }
+}
+
+impl<I, O> Unpin for Eventually<I, O> {}
+
+impl<I: PartialEq, O> Future for Eventually<I, O> {
+    type Output = Validator<I, O>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Validator<I, O>> {
+        let validator = self
+            .validator
+            .as_ref()
+            .expect("Eventually polled after completion");
+
+        // Register before checking, so a call made in between still wakes us
+        validator.signal().register(cx.waker());
+        if validator.holds() || Instant::now() >= self.deadline {
+            let validator = self.validator.take().unwrap();
+            self.timer = None;
+            return Poll::Ready(validator.settle());
+        }
+
+        if self.timer.is_none() {
+            self.timer = Some(notify_at(validator.signal(), self.deadline));
+        }
+
+        Poll::Pending
+    }
+}
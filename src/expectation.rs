@@ -0,0 +1,71 @@
+use crate::sync::lock;
+use std::sync::Mutex;
+
+type Check = Box<dyn Fn() -> Option<String> + Send>;
+
+/// Expectations checked when the last clone of a mock is dropped
+pub(crate) struct Expectations {
+    name: String,
+    checks: Mutex<Vec<Check>>,
+    /// Whether the mock is kept for its thread, and dropped when the thread
+    /// exits, where a failure can't fail the test
+    per_thread: bool,
+}
+
+impl Expectations {
+    pub(crate) fn new(name: String) -> Expectations {
+        Expectations {
+            name,
+            checks: Mutex::new(Vec::new()),
+            per_thread: false,
+        }
+    }
+
+    pub(crate) fn per_thread(name: String) -> Expectations {
+        Expectations {
+            name,
+            checks: Mutex::new(Vec::new()),
+            per_thread: true,
+        }
+    }
+
+    /// Register a check returning a failure message when the expectation is
+    /// not met
+    pub(crate) fn push(&self, check: Check) {
+        if self.per_thread {
+            panic!(
+                "Mock \"{}\" is kept for its thread, which drops it too late to fail the test: \
+                 verify the expectation now, or enter a `StaticMocks` to verify it on drop",
+                self.name
+            );
+        }
+        lock(&self.checks).push(check);
+    }
+}
+
+impl std::fmt::Debug for Expectations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let checks = lock(&self.checks);
+        write!(f, "Expectations({})", checks.len())
+    }
+}
+
+impl Drop for Expectations {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+
+        // The checks run user code, so they run once the list is unlocked
+        let checks = std::mem::take(&mut *lock(&self.checks));
+        let failures: Vec<String> = checks.iter().filter_map(|check| check()).collect();
+
+        if !failures.is_empty() {
+            panic!(
+                "Mock \"{}\" dropped with unmet expectations: {}",
+                self.name,
+                failures.join(", ")
+            );
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use crate::call::History;
+use crate::local::LocalMock;
+use crate::mock::Mock;
+use crate::sync::lock;
+use std::any::{type_name, Any, TypeId};
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// The mocks of a generic method, one per instantiation of its type
+/// parameters.
+///
+/// Each instantiation is a regular `Mock`, named after the method and the
+/// type parameters, so a failure tells which type was not configured.
+#[derive(Clone)]
+pub struct GenericMock {
+    name: String,
+    instances: Arc<Mutex<Instances<Arc<dyn Instance + Send + Sync>>>>,
+}
+
+/// The mocks of a generic method of a `LocalMock` based mock
+#[derive(Clone)]
+pub struct LocalGenericMock {
+    name: String,
+    instances: Rc<RefCell<Instances<Rc<dyn Instance>>>>,
+}
+
+/// What the mock struct does with every instantiation of a generic method
+trait Instance {
+    fn as_any(&self) -> &dyn Any;
+    fn set_history(&self, history: History);
+    fn verify_no_more_interactions(&self);
+    fn assert_all_stubs_used(&self);
+}
+
+struct Instances<P> {
+    history: History,
+    mocks: Vec<(TypeId, P)>,
+}
+
+impl<P> Instances<P> {
+    fn new() -> Instances<P> {
+        Instances {
+            history: History::default(),
+            mocks: Vec::new(),
+        }
+    }
+
+    fn get(&self, key: TypeId) -> Option<&P> {
+        self.mocks
+            .iter()
+            .find(|(other, _mock)| *other == key)
+            .map(|(_key, mock)| mock)
+    }
+}
+
+impl<I: Debug + 'static, O: 'static> Instance for Mock<I, O> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn set_history(&self, history: History) {
+        self.clone().with_history(history);
+    }
+
+    fn verify_no_more_interactions(&self) {
+        Mock::verify_no_more_interactions(self)
+    }
+
+    fn assert_all_stubs_used(&self) {
+        Mock::assert_all_stubs_used(self)
+    }
+}
+
+impl<I: Debug + 'static, O: 'static> Instance for LocalMock<I, O> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn set_history(&self, history: History) {
+        self.clone().with_history(history);
+    }
+
+    fn verify_no_more_interactions(&self) {
+        LocalMock::verify_no_more_interactions(self)
+    }
+
+    fn assert_all_stubs_used(&self) {
+        LocalMock::assert_all_stubs_used(self)
+    }
+}
+
+/// Name of the instantiation of a generic method for the type parameters `K`
+fn instance_name<K: ?Sized>(name: &str) -> String {
+    format!("{}::<{}>", name, type_name::<K>())
+}
+
+impl GenericMock {
+    pub fn new(name: String) -> GenericMock {
+        GenericMock {
+            name,
+            instances: Arc::new(Mutex::new(Instances::new())),
+        }
+    }
+
+    /// Configure how much call history the mock of every instantiation keeps
+    pub fn with_history(self, history: History) -> GenericMock {
+        let mocks = {
+            let mut instances = lock(&self.instances);
+            instances.history = history;
+            instances.mocks.clone()
+        };
+        mocks
+            .iter()
+            .for_each(|(_key, mock)| mock.set_history(history));
+
+        self
+    }
+
+    /// The mock of the instantiation for the type parameters `K`, created
+    /// from its name on first use
+    pub fn of<K, I, O>(&self, new: impl FnOnce(String) -> Mock<I, O>) -> Mock<I, O>
+    where
+        K: ?Sized + 'static,
+        I: Debug + Send + 'static,
+        O: Send + 'static,
+    {
+        let mut instances = lock(&self.instances);
+        if let Some(mock) = instances.get(TypeId::of::<K>()) {
+            return mock
+                .as_any()
+                .downcast_ref::<Mock<I, O>>()
+                .expect("Instantiations are registered by type")
+                .clone();
+        }
+
+        let mock = new(instance_name::<K>(&self.name)).with_history(instances.history);
+        instances
+            .mocks
+            .push((TypeId::of::<K>(), Arc::new(mock.clone())));
+        mock
+    }
+
+    /// Fail if any instantiation has a call not matched by a verified
+    /// expectation
+    pub fn verify_no_more_interactions(&self) {
+        let mocks = lock(&self.instances).mocks.clone();
+        mocks
+            .iter()
+            .for_each(|(_key, mock)| mock.verify_no_more_interactions());
+    }
+
+    /// Fail if any instantiation has a rule never used to answer a call
+    pub fn assert_all_stubs_used(&self) {
+        let mocks = lock(&self.instances).mocks.clone();
+        mocks
+            .iter()
+            .for_each(|(_key, mock)| mock.assert_all_stubs_used());
+    }
+}
+
+impl Debug for GenericMock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let instances = lock(&self.instances);
+        write!(
+            f,
+            "GenericMock({}, {} instances)",
+            self.name,
+            instances.mocks.len()
+        )
+    }
+}
+
+impl LocalGenericMock {
+    pub fn new(name: String) -> LocalGenericMock {
+        LocalGenericMock {
+            name,
+            instances: Rc::new(RefCell::new(Instances::new())),
+        }
+    }
+
+    /// Configure how much call history the mock of every instantiation keeps
+    pub fn with_history(self, history: History) -> LocalGenericMock {
+        let mocks = {
+            let mut instances = self.instances.borrow_mut();
+            instances.history = history;
+            instances.mocks.clone()
+        };
+        mocks
+            .iter()
+            .for_each(|(_key, mock)| mock.set_history(history));
+
+        self
+    }
+
+    /// The mock of the instantiation for the type parameters `K`, created
+    /// from its name on first use
+    pub fn of<K, I, O>(&self, new: impl FnOnce(String) -> LocalMock<I, O>) -> LocalMock<I, O>
+    where
+        K: ?Sized + 'static,
+        I: Debug + 'static,
+        O: 'static,
+    {
+        let mut instances = self.instances.borrow_mut();
+        if let Some(mock) = instances.get(TypeId::of::<K>()) {
+            return mock
+                .as_any()
+                .downcast_ref::<LocalMock<I, O>>()
+                .expect("Instantiations are registered by type")
+                .clone();
+        }
+
+        let mock = new(instance_name::<K>(&self.name)).with_history(instances.history);
+        instances
+            .mocks
+            .push((TypeId::of::<K>(), Rc::new(mock.clone())));
+        mock
+    }
+
+    /// Fail if any instantiation has a call not matched by a verified
+    /// expectation
+    pub fn verify_no_more_interactions(&self) {
+        let mocks = self.instances.borrow().mocks.clone();
+        mocks
+            .iter()
+            .for_each(|(_key, mock)| mock.verify_no_more_interactions());
+    }
+
+    /// Fail if any instantiation has a rule never used to answer a call
+    pub fn assert_all_stubs_used(&self) {
+        let mocks = self.instances.borrow().mocks.clone();
+        mocks
+            .iter()
+            .for_each(|(_key, mock)| mock.assert_all_stubs_used());
+    }
+}
+
+impl Debug for LocalGenericMock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let instances = self.instances.borrow();
+        write!(
+            f,
+            "LocalGenericMock({}, {} instances)",
+            self.name,
+            instances.mocks.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn instantiations_are_shared_by_type() {
+        let mock = GenericMock::new("A.get".to_string());
+        mock.of::<u8, i32, u8>(Mock::new).when(1).will_return(2);
+
+        assert_eq!(mock.clone().of::<u8, i32, u8>(Mock::new).called(1), 2);
+        assert_eq!(mock.of::<u16, i32, u16>(Mock::new).call_count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Mock \"A.get::<u16>\" called with unexpected input")]
+    fn unconfigured_instantiation_should_panic_with_its_type() {
+        let mock = GenericMock::new("A.get".to_string());
+        mock.of::<u8, i32, u8>(Mock::new).when(1).will_return(2);
+
+        mock.of::<u16, i32, u16>(Mock::new).called(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Mock \"A.get::<u8>\" has unverified calls: 1")]
+    fn verify_every_instantiation() {
+        let mock = GenericMock::new("A.get".to_string());
+        mock.of::<u8, i32, u8>(Mock::new).when(1).will_return(2);
+        mock.of::<u8, i32, u8>(Mock::new).called(1);
+
+        mock.verify_no_more_interactions();
+    }
+
+    #[test]
+    fn local_instantiations_are_shared_by_type() {
+        let mock = LocalGenericMock::new("A.get".to_string()).with_history(History::CountOnly);
+        mock.of::<u8, i32, Rc<u8>>(LocalMock::new)
+            .when(1)
+            .will_return(Rc::new(2));
+
+        assert_eq!(*mock.of::<u8, i32, Rc<u8>>(LocalMock::new).called(1), 2);
+        assert_eq!(
+            mock.of::<u8, i32, Rc<u8>>(LocalMock::new).calls(),
+            Vec::<i32>::new()
+        );
+    }
+}
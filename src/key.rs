@@ -0,0 +1,79 @@
+use crate::matcher::Matcher;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Inputs an indexed mock can look up by hash: matchers of `Hash + Eq`
+/// values, and tuples of them. `Matcher` itself isn't `Hash`, as `Any`
+/// equals every value: an input holding it has no key and is compared with
+/// every call or rule instead of being looked up.
+pub trait Key {
+    /// Feed the input to the hasher, or `None` when it holds a wildcard
+    fn hash_key(&self, hasher: &mut DefaultHasher) -> Option<()>;
+}
+
+impl<T: Hash + Eq> Key for Matcher<T> {
+    fn hash_key(&self, hasher: &mut DefaultHasher) -> Option<()> {
+        match self {
+            Matcher::Val(val) => {
+                val.hash(hasher);
+                Some(())
+            }
+            Matcher::Any => None,
+        }
+    }
+}
+
+impl Key for () {
+    fn hash_key(&self, _hasher: &mut DefaultHasher) -> Option<()> {
+        Some(())
+    }
+}
+
+macro_rules! key_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Key),+> Key for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn hash_key(&self, hasher: &mut DefaultHasher) -> Option<()> {
+                let ($($name,)+) = self;
+                $($name.hash_key(hasher)?;)+
+                Some(())
+            }
+        }
+    };
+}
+
+key_tuple!(A);
+key_tuple!(A, B);
+key_tuple!(A, B, C);
+key_tuple!(A, B, C, D);
+key_tuple!(A, B, C, D, E);
+key_tuple!(A, B, C, D, E, F);
+key_tuple!(A, B, C, D, E, F, G);
+key_tuple!(A, B, C, D, E, F, G, H);
+key_tuple!(A, B, C, D, E, F, G, H, I);
+key_tuple!(A, B, C, D, E, F, G, H, I, J);
+key_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+key_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// The key of the input in an indexed mock, or `None` when it holds a
+/// wildcard and can't be looked up by hash
+pub(crate) fn key<I: Key>(input: &I) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    input.hash_key(&mut hasher)?;
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matcher::{any, eq};
+
+    #[test]
+    fn keys() {
+        assert_eq!(key(&eq(5)), key(&eq(5)));
+        assert_ne!(key(&eq(5)), key(&eq(6)));
+        assert_eq!(key(&any::<i32>()), None);
+        assert_eq!(key(&(eq(5), eq("a"))), key(&(eq(5), eq("a"))));
+        assert_eq!(key(&(eq(5), any::<&str>())), None);
+    }
+}
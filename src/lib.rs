@@ -1,11 +1,35 @@
+pub use crate::arguments::Arguments;
+pub use crate::call::{Call, History, Outcome};
+pub use crate::eventually::Eventually;
+pub use crate::generic::{GenericMock, LocalGenericMock};
+pub use crate::local::{LocalMock, LocalValidator, LocalWhen};
 pub use crate::matcher::*;
 pub use crate::mock::*;
-pub use crate::validator::{verify, Validator};
+pub use crate::registry::{reset_per_thread_mocks, Entered, Scoped, StaticMocks};
+pub use crate::retained::SharedOutput;
+pub use crate::subscription::Subscription;
+pub use crate::validator::{verify, Validator, Verify};
 pub use crate::when::*;
 pub use mock_it_codegen::*;
 
+mod arguments;
+mod call;
+mod closure;
+mod eventually;
+mod expectation;
+mod generic;
+mod key;
+mod local;
 mod matcher;
 mod mock;
+mod registry;
+mod retained;
 mod rule;
+mod signal;
+mod state;
+mod storage;
+mod subscription;
+mod sync;
+mod timer;
 mod validator;
 mod when;
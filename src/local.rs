@@ -0,0 +1,272 @@
+use crate::arguments::Arguments;
+use crate::call::{Call, History};
+use crate::key::Key;
+use crate::registry::thread_local;
+use crate::retained::SharedOutput;
+use crate::rule::{configure, Rules};
+use crate::state::MockState;
+use crate::storage::Local;
+use crate::validator::{Check, Verify};
+
+/// A single-threaded mock, for inputs and outputs that aren't `Send` such as
+/// `Rc` or `RefCell`. Its clones share their state like the clones of `Mock`,
+/// without paying for locking.
+#[derive(Debug)]
+pub struct LocalMock<I, O> {
+    state: MockState<Local, I, O>,
+}
+
+impl<I, O> Clone for LocalMock<I, O> {
+    fn clone(&self) -> LocalMock<I, O> {
+        LocalMock {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<I, O> LocalMock<I, O> {
+    pub fn new(name: String) -> LocalMock<I, O> {
+        LocalMock {
+            state: MockState::new(name),
+        }
+    }
+
+    /// Configure how much call history the mock keeps
+    pub fn with_history(self, history: History) -> LocalMock<I, O> {
+        self.state.set_history(history);
+        self
+    }
+
+    /// Number of times the mock was called, including the calls discarded by
+    /// its history
+    pub fn call_count(&self) -> usize {
+        self.state.call_count()
+    }
+}
+
+impl<I: 'static, O: 'static> LocalMock<I, O> {
+    /// The mock registered under the module and name for the current thread,
+    /// created on first use. Functions without a receiver have no mock
+    /// instance to keep their mock, so they share this one.
+    pub fn per_thread(module: &'static str, name: String) -> LocalMock<I, O> {
+        thread_local(module, name, LocalMock::new)
+    }
+}
+
+impl<I: Arguments, O> LocalMock<I, O> {
+    /// Name the arguments of the mock, so a failure on an unexpected input
+    /// compares it argument by argument with the closest rule
+    pub fn with_arg_names(self, names: &[&str]) -> LocalMock<I, O> {
+        LocalMock {
+            state: self.state.with_arg_names(names),
+        }
+    }
+}
+
+impl<I: Key, O> LocalMock<I, O> {
+    /// Create a mock looking up its rules and calls by the hash of their
+    /// input instead of scanning them, for large stub tables. The inputs are
+    /// matchers of `Hash + Eq` values, or tuples of them. Inputs holding
+    /// `Matcher::Any` are still compared with every rule or call.
+    pub fn indexed(name: String) -> LocalMock<I, O> {
+        LocalMock {
+            state: MockState::indexed(name),
+        }
+    }
+}
+
+impl<I: Key + 'static, O: 'static> LocalMock<I, O> {
+    /// The indexed mock registered under the module and name for the current
+    /// thread, created on first use
+    pub fn per_thread_indexed(module: &'static str, name: String) -> LocalMock<I, O> {
+        thread_local(module, name, LocalMock::indexed)
+    }
+}
+
+impl<I, O: Clone> LocalMock<I, O> {
+    pub fn when(&self, input: I) -> LocalWhen<I, O> {
+        LocalWhen {
+            input,
+            rules: self.state.rules.clone(),
+        }
+    }
+
+    pub fn was_called_with(&self, input: I) -> LocalValidator<I, O> {
+        LocalValidator {
+            check: Check::new(self.state.calls.clone(), input),
+        }
+    }
+}
+
+impl<I: PartialEq + std::fmt::Debug, O: Clone> LocalMock<I, O> {
+    #[track_caller]
+    pub fn called(&self, input: I) -> O {
+        self.state.called(input, || {})
+    }
+
+    /// A closure forwarding its input to the mock, for APIs taking callbacks
+    pub fn as_fn(&self) -> impl Fn(I) -> O {
+        let mock = self.clone();
+        move |input| mock.called(input)
+    }
+
+    /// A mutable closure forwarding its input to the mock
+    pub fn as_fn_mut(&self) -> impl FnMut(I) -> O {
+        self.as_fn()
+    }
+}
+
+impl<I: PartialEq + std::fmt::Debug, O: Clone + SharedOutput> LocalMock<I, O> {
+    /// Call the mock, keeping the output for as long as the mock so it can be
+    /// borrowed, for methods returning references. A single clone of each
+    /// configured output is kept, until the mock is dropped.
+    #[track_caller]
+    pub fn called_ref(&self, input: I) -> &O {
+        self.state.called_ref(input, || {})
+    }
+}
+
+impl<I: Clone, O> LocalMock<I, O> {
+    /// Snapshot of the inputs the mock was called with, in call order, as kept
+    /// by its history
+    pub fn calls(&self) -> Vec<I> {
+        self.state.calls()
+    }
+
+    /// Input of the last call to the mock
+    pub fn last_call(&self) -> Option<I> {
+        self.state.last_call()
+    }
+
+    /// Input of the call at the index, starting from zero, if it was kept by
+    /// the history
+    pub fn nth_call(&self, index: usize) -> Option<I> {
+        self.state.nth_call(index)
+    }
+}
+
+impl<I: Clone, O: Clone> LocalMock<I, O> {
+    /// Snapshot of the calls made to the mock with their outcome, in call
+    /// order, as kept by its history
+    pub fn call_records(&self) -> Vec<Call<I, O>> {
+        self.state.call_records()
+    }
+
+    /// The last call to the mock with its outcome
+    pub fn last_call_record(&self) -> Option<Call<I, O>> {
+        self.state.last_call_record()
+    }
+
+    /// The call at the index with its outcome, starting from zero, if it was
+    /// kept by the history
+    pub fn nth_call_record(&self, index: usize) -> Option<Call<I, O>> {
+        self.state.nth_call_record(index)
+    }
+}
+
+impl<I: std::fmt::Debug, O> LocalMock<I, O> {
+    /// Fail if any recorded call was not matched by a verified expectation
+    pub fn verify_no_more_interactions(&self) {
+        self.state.verify_no_more_interactions()
+    }
+
+    /// Fail if any configured rule was never used to answer a call
+    pub fn assert_all_stubs_used(&self) {
+        self.state.assert_all_stubs_used()
+    }
+}
+
+/// Configures the output of a `LocalMock` for an input
+pub struct LocalWhen<I, O> {
+    input: I,
+    rules: Rules<Local, I, O>,
+}
+
+impl<I: PartialEq, O> LocalWhen<I, O> {
+    /// Use the when return value when the mock is called with the specified
+    /// input
+    pub fn will_return(self, value: O) {
+        configure::<Local, _, _>(&self.rules, self.input, value)
+    }
+}
+
+impl<I: PartialEq, O: Default> LocalWhen<I, O> {
+    /// Use `Default::default` when the mock is called with the specified input
+    pub fn will_return_default(self) {
+        self.will_return(O::default())
+    }
+}
+
+/// Verifies the calls made to a `LocalMock`
+pub struct LocalValidator<I, O = ()> {
+    check: Check<Local, I, O>,
+}
+
+impl<I: PartialEq, O> LocalValidator<I, O> {
+    pub fn result(&mut self) -> bool {
+        self.check.result()
+    }
+    pub fn times(mut self, times: usize) -> LocalValidator<I, O> {
+        self.check.times(times);
+        self
+    }
+    pub fn called(self) -> bool {
+        let mut this = self;
+        this.result()
+    }
+}
+
+impl<I: PartialEq, O> Verify for LocalValidator<I, O> {
+    fn called(self) -> bool {
+        LocalValidator::called(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::validator::verify;
+    use std::rc::Rc;
+
+    trait ATrait {
+        fn int_to_rc(&self, input: Rc<i64>) -> Rc<String>;
+    }
+
+    #[derive(Clone)]
+    struct MyMock {
+        int_to_rc: LocalMock<Rc<i64>, Rc<String>>,
+    }
+
+    impl ATrait for MyMock {
+        fn int_to_rc(&self, input: Rc<i64>) -> Rc<String> {
+            self.int_to_rc.called(input)
+        }
+    }
+
+    #[test]
+    fn local_mock_output() {
+        let mock = MyMock {
+            int_to_rc: LocalMock::new("AMockName".to_string()),
+        };
+        mock.int_to_rc
+            .when(Rc::new(65))
+            .will_return(Rc::new(String::from("65")));
+        let a_trait = Box::new(mock.clone());
+
+        assert_eq!("65", *a_trait.int_to_rc(Rc::new(65)));
+        assert!(verify(mock.int_to_rc.was_called_with(Rc::new(65)).times(1)));
+        assert!(!verify(mock.int_to_rc.was_called_with(Rc::new(63))));
+        mock.int_to_rc.verify_no_more_interactions();
+        mock.int_to_rc.assert_all_stubs_used();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Mock \"AMockName\" called with unexpected input: \"63\", did you forget to configure your mock ?"
+    )]
+    fn local_mock_no_when_should_panic() {
+        let mock: LocalMock<Rc<i64>, Rc<String>> = LocalMock::new("AMockName".to_string());
+
+        mock.called(Rc::new(63));
+    }
+}
@@ -1,3 +1,4 @@
+#[derive(Clone)]
 pub enum Matcher<I> {
     Val(I),
     Any,
@@ -17,7 +18,7 @@ impl<I: PartialEq> PartialEq for Matcher<I> {
         use crate::matcher::Matcher::*;
 
         match (self, other) {
-            (&Val(ref a), &Val(ref b)) => a == b,
+            (Val(a), Val(b)) => a == b,
             _ => true,
         }
     }
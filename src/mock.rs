@@ -1,73 +1,222 @@
-use crate::rule::Rule;
+use crate::arguments::Arguments;
+use crate::call::{Call, History};
+use crate::expectation::Expectations;
+use crate::key::Key;
+use crate::registry::registered;
+use crate::retained::SharedOutput;
+use crate::signal::Signal;
+use crate::state::MockState;
+use crate::storage::Threaded;
+use crate::subscription::Subscription;
 use crate::validator::*;
 use crate::when::When;
 use std::sync::Arc;
-use std::sync::Mutex;
 
 #[derive(Debug)]
 pub struct Mock<I, O> {
-    name: String,
-    calls: Arc<Mutex<Vec<I>>>,
-    rules: Arc<Mutex<Vec<Rule<I, O>>>>,
+    state: MockState<Threaded, I, O>,
+    expectations: Arc<Expectations>,
+    signal: Arc<Signal>,
 }
 
 impl<I, O> Clone for Mock<I, O> {
     fn clone(&self) -> Mock<I, O> {
         Mock {
-            name: self.name.clone(),
-            calls: self.calls.clone(),
-            rules: self.rules.clone(),
+            state: self.state.clone(),
+            expectations: self.expectations.clone(),
+            signal: self.signal.clone(),
         }
     }
 }
 
 impl<I, O> Mock<I, O> {
     pub fn new(name: String) -> Mock<I, O> {
+        Mock::with_state(MockState::new(name))
+    }
+
+    fn with_state(state: MockState<Threaded, I, O>) -> Mock<I, O> {
+        Mock {
+            expectations: Arc::new(Expectations::new(state.name.clone())),
+            state,
+            signal: Arc::new(Signal::default()),
+        }
+    }
+}
+
+impl<I, O> Mock<I, O> {
+    /// The mock, kept for the current thread until it exits, which is too
+    /// late to fail a test on unmet expectations
+    pub(crate) fn kept_per_thread(self) -> Mock<I, O> {
         Mock {
-            name,
-            calls: Arc::new(Mutex::new(Vec::new())),
-            rules: Arc::new(Mutex::new(Vec::new())),
+            expectations: Arc::new(Expectations::per_thread(self.state.name.clone())),
+            ..self
         }
     }
+
+    /// Configure how much call history the mock keeps
+    pub fn with_history(self, history: History) -> Mock<I, O> {
+        self.state.set_history(history);
+        self
+    }
+}
+
+impl<I: Send + 'static, O: Send + 'static> Mock<I, O> {
+    /// The mock registered under the module and name in the entered
+    /// `StaticMocks`, or else for the current thread, created on first use.
+    /// Functions without a receiver have no mock instance to keep their mock,
+    /// so they share this one. A mock kept for the current thread can't
+    /// verify expectations on drop.
+    pub fn registered(module: &'static str, name: String) -> Mock<I, O> {
+        registered(module, name, Mock::new)
+    }
+}
+
+impl<I: Arguments, O> Mock<I, O> {
+    /// Name the arguments of the mock, so a failure on an unexpected input
+    /// compares it argument by argument with the closest rule
+    pub fn with_arg_names(self, names: &[&str]) -> Mock<I, O> {
+        Mock {
+            state: self.state.with_arg_names(names),
+            ..self
+        }
+    }
+}
+
+impl<I: Key, O> Mock<I, O> {
+    /// Create a mock looking up its rules and calls by the hash of their
+    /// input instead of scanning them, for large stub tables. The inputs are
+    /// matchers of `Hash + Eq` values, or tuples of them. Inputs holding
+    /// `Matcher::Any` are still compared with every rule or call.
+    pub fn indexed(name: String) -> Mock<I, O> {
+        Mock::with_state(MockState::indexed(name))
+    }
+}
+
+impl<I: Key + Send + 'static, O: Send + 'static> Mock<I, O> {
+    /// The indexed mock registered under the module and name, like
+    /// `Mock::registered`
+    pub fn registered_indexed(module: &'static str, name: String) -> Mock<I, O> {
+        registered(module, name, Mock::indexed)
+    }
 }
 
 impl<I, O: Clone> Mock<I, O> {
     pub fn when(&self, input: I) -> When<I, O> {
-        When::new(input, self.rules.clone())
+        When::new(input, self.state.rules.clone())
     }
 }
 
 impl<I: PartialEq + std::fmt::Debug, O: Clone> Mock<I, O> {
+    #[track_caller]
     pub fn called(&self, input: I) -> O {
-        let input_str = format!("{:?}", input);
-
-        // Get the when value for this input
-        let rules = self.rules.lock().unwrap();
-        let when_value = rules.iter().find(|value| value.input == input);
-
-        // Record this call
-        self.calls.lock().unwrap().push(input);
-
-        // Return the when value, or fail if there is no when value
-        match when_value {
-            Some(value) => value.output.clone(),
-            None => panic!(
-                "Mock \"{}\" called with unexpected input: {:?}, did you forget to configure your mock ?",
-                self.name, input_str
-            ),
-        }
+        self.state.called(input, || self.signal.notify())
+    }
+
+    /// A closure forwarding its input to the mock, for APIs taking callbacks
+    pub fn as_fn(&self) -> impl Fn(I) -> O {
+        let mock = self.clone();
+        move |input| mock.called(input)
+    }
+
+    /// A mutable closure forwarding its input to the mock
+    pub fn as_fn_mut(&self) -> impl FnMut(I) -> O {
+        self.as_fn()
     }
 }
 
 impl<I, O: Clone> Mock<I, O> {
-    pub fn was_called_with(&self, input: I) -> Validator<I> {
-        Validator::new(self.calls.clone(), input)
+    pub fn was_called_with(&self, input: I) -> Validator<I, O> {
+        Validator::for_calls(self.state.calls.clone(), input)
+            .expected_by(&self.expectations)
+            .signaled_by(&self.signal)
+    }
+}
+
+impl<I: PartialEq + std::fmt::Debug, O: Clone + SharedOutput + Sync> Mock<I, O> {
+    /// Call the mock, keeping the output for as long as the mock so it can be
+    /// borrowed, for methods returning references. A single clone of each
+    /// configured output is kept, until the mock is dropped.
+    #[track_caller]
+    pub fn called_ref(&self, input: I) -> &O {
+        self.state.called_ref(input, || self.signal.notify())
+    }
+}
+
+impl<I: Clone, O> Mock<I, O> {
+    /// Snapshot of the inputs the mock was called with, in call order, as kept
+    /// by its history
+    pub fn calls(&self) -> Vec<I> {
+        self.state.calls()
+    }
+
+    /// Input of the last call to the mock
+    pub fn last_call(&self) -> Option<I> {
+        self.state.last_call()
+    }
+
+    /// Input of the call at the index, starting from zero, if it was kept by
+    /// the history
+    pub fn nth_call(&self, index: usize) -> Option<I> {
+        self.state.nth_call(index)
+    }
+}
+
+impl<I: Clone, O: Clone> Mock<I, O> {
+    /// Snapshot of the calls made to the mock with their outcome, in call
+    /// order, as kept by its history
+    pub fn call_records(&self) -> Vec<Call<I, O>> {
+        self.state.call_records()
+    }
+
+    /// The last call to the mock with its outcome
+    pub fn last_call_record(&self) -> Option<Call<I, O>> {
+        self.state.last_call_record()
+    }
+
+    /// The call at the index with its outcome, starting from zero, if it was
+    /// kept by the history
+    pub fn nth_call_record(&self, index: usize) -> Option<Call<I, O>> {
+        self.state.nth_call_record(index)
+    }
+}
+
+impl<I, O> Mock<I, O> {
+    /// Number of times the mock was called, including the calls discarded by
+    /// its history
+    pub fn call_count(&self) -> usize {
+        self.state.call_count()
+    }
+
+    /// Receive every call made to the mock from now on
+    pub fn subscribe(&self) -> Subscription<I, O> {
+        Subscription::new(self.state.calls.clone(), self.signal.clone())
+    }
+}
+
+impl<I: Clone, O> Mock<I, O> {
+    /// Wait for the next call to the mock and return its input
+    pub async fn next_call(&self) -> I {
+        self.subscribe().next_call().await
+    }
+}
+
+impl<I: std::fmt::Debug, O> Mock<I, O> {
+    /// Fail if any recorded call was not matched by a verified expectation
+    pub fn verify_no_more_interactions(&self) {
+        self.state.verify_no_more_interactions()
+    }
+
+    /// Fail if any configured rule was never used to answer a call
+    pub fn assert_all_stubs_used(&self) {
+        self.state.assert_all_stubs_used()
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::call::Outcome;
+    use crate::matcher::{any, eq, Matcher};
 
     trait ATrait {
         fn int_to_string(&self, input: i64) -> String;
@@ -136,21 +285,418 @@ mod test {
             a_trait.int_to_string(65);
         }
 
-        assert_eq!(
-            true,
-            verify(mock.int_to_string.was_called_with(65).times(5))
-        );
-        assert_eq!(
-            false,
-            verify(mock.int_to_string.was_called_with(65).times(4))
-        );
-        assert_eq!(
-            false,
-            verify(mock.int_to_string.was_called_with(65).times(1))
+        assert!(verify(mock.int_to_string.was_called_with(65).times(5)));
+        assert!(!verify(mock.int_to_string.was_called_with(65).times(4)));
+        assert!(!verify(mock.int_to_string.was_called_with(65).times(1)));
+        assert!(!verify(mock.int_to_string.was_called_with(65).times(6)));
+    }
+
+    #[test]
+    fn verify_no_more_interactions_when_every_call_is_verified() {
+        let mock = MyMock::new();
+        mock.int_to_string.when(65).will_return(String::from("65"));
+
+        mock.int_to_string(65);
+        mock.int_to_string(65);
+
+        assert!(verify(mock.int_to_string.was_called_with(65).times(2)));
+        mock.int_to_string.verify_no_more_interactions();
+    }
+
+    #[test]
+    #[should_panic(expected = "Mock \"AMockName\" has unverified calls: 63")]
+    fn verify_no_more_interactions_with_unverified_call_should_panic() {
+        let mock = MyMock::new();
+        mock.int_to_string.when(65).will_return(String::from("65"));
+        mock.int_to_string.when(63).will_return(String::from("63"));
+
+        mock.int_to_string(65);
+        mock.int_to_string(63);
+
+        assert!(verify(mock.int_to_string.was_called_with(65)));
+        mock.int_to_string.verify_no_more_interactions();
+    }
+
+    #[test]
+    fn assert_all_stubs_used_when_every_rule_is_hit() {
+        let mock = MyMock::new();
+        mock.int_to_string.when(65).will_return(String::from("65"));
+        mock.int_to_string.when(63).will_return(String::from("63"));
+
+        mock.int_to_string(65);
+        mock.int_to_string(63);
+
+        mock.int_to_string.assert_all_stubs_used();
+    }
+
+    #[test]
+    #[should_panic(expected = "Mock \"AMockName\" has unused stubs: 63, -1")]
+    fn assert_all_stubs_used_with_unused_rules_should_panic() {
+        let mock = MyMock::new();
+        mock.int_to_string.when(65).will_return(String::from("65"));
+        mock.int_to_string.when(63).will_return(String::from("63"));
+        mock.int_to_string.when(-1).will_return(String::from("-1"));
+
+        mock.int_to_string(65);
+
+        mock.int_to_string.assert_all_stubs_used();
+    }
+
+    #[test]
+    fn verify_on_drop_when_expectation_is_met() {
+        let mock = MyMock::new();
+        mock.int_to_string.when(65).will_return(String::from("65"));
+        mock.int_to_string
+            .was_called_with(65)
+            .times(2)
+            .verify_on_drop();
+
+        mock.int_to_string(65);
+        mock.int_to_string(65);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Mock \"AMockName\" dropped with unmet expectations: 65 expected 2 times, called 1 times"
+    )]
+    fn verify_on_drop_when_expectation_is_not_met_should_panic() {
+        let mock = MyMock::new();
+        mock.int_to_string.when(65).will_return(String::from("65"));
+        mock.int_to_string
+            .was_called_with(65)
+            .times(2)
+            .verify_on_drop();
+        let a_trait = Box::new(mock.clone());
+
+        a_trait.int_to_string(65);
+        drop(mock);
+    }
+
+    #[test]
+    fn verify_on_drop_waits_for_the_last_clone() {
+        let mock = MyMock::new();
+        mock.int_to_string.when(65).will_return(String::from("65"));
+        mock.int_to_string.was_called_with(65).verify_on_drop();
+        let a_trait = Box::new(mock.clone());
+
+        drop(mock);
+        a_trait.int_to_string(65);
+    }
+
+    #[test]
+    fn inspect_calls() {
+        let mock = MyMock::new();
+        mock.int_to_string.when(65).will_return(String::from("65"));
+        mock.int_to_string.when(63).will_return(String::from("63"));
+
+        assert_eq!(mock.int_to_string.call_count(), 0);
+        assert_eq!(mock.int_to_string.last_call(), None);
+
+        mock.int_to_string(65);
+        mock.int_to_string(63);
+        mock.int_to_string(65);
+
+        assert_eq!(mock.int_to_string.calls(), vec![65, 63, 65]);
+        assert_eq!(mock.int_to_string.call_count(), 3);
+        assert_eq!(mock.int_to_string.last_call(), Some(65));
+        assert_eq!(mock.int_to_string.nth_call(1), Some(63));
+        assert_eq!(mock.int_to_string.nth_call(3), None);
+    }
+
+    #[test]
+    fn subscription_receives_new_calls() {
+        let mock = MyMock::new();
+        mock.int_to_string.when(65).will_return(String::from("65"));
+        mock.int_to_string.when(63).will_return(String::from("63"));
+        mock.int_to_string(65);
+
+        let mut subscription = mock.int_to_string.subscribe();
+        assert_eq!(subscription.try_next(), None);
+
+        mock.int_to_string(63);
+        mock.int_to_string(65);
+
+        assert_eq!(subscription.try_next(), Some(63));
+        assert_eq!(subscription.try_next(), Some(65));
+        assert_eq!(subscription.try_next(), None);
+    }
+
+    #[derive(Debug)]
+    struct PanickyClone(bool);
+
+    impl Clone for PanickyClone {
+        fn clone(&self) -> Self {
+            if self.0 {
+                panic!("clone failed");
+            }
+            PanickyClone(false)
+        }
+    }
+
+    /// A panic while the mock holds a lock only fails the call that caused it
+    #[test]
+    fn mock_keeps_working_after_a_panic_while_locked() {
+        let mock: Mock<i64, PanickyClone> = Mock::new("AMockName".to_string());
+        mock.when(1).will_return(PanickyClone(true));
+        mock.when(2).will_return(PanickyClone(false));
+
+        let a_mock = mock.clone();
+        let result = std::thread::spawn(move || a_mock.called(1)).join();
+        assert!(result.is_err());
+
+        mock.called(2);
+        mock.when(3).will_return(PanickyClone(false));
+        assert!(verify(mock.was_called_with(2)));
+
+        // The panicking clone is recorded as the outcome of the first call
+        assert_eq!(mock.call_count(), 2);
+        let outcome = mock.nth_call_record(0).map(|call| call.outcome);
+        assert!(matches!(outcome, Some(Outcome::Panicked(message)) if message == "clone failed"));
+    }
+
+    #[derive(Debug)]
+    struct Reentrant {
+        mock: Option<Mock<i64, Reentrant>>,
+    }
+
+    /// Cloning the output calls back into the mock that returns it
+    impl Clone for Reentrant {
+        fn clone(&self) -> Self {
+            if let Some(mock) = &self.mock {
+                mock.called(0);
+            }
+            Reentrant { mock: None }
+        }
+    }
+
+    #[test]
+    fn mock_can_be_called_from_inside_its_own_stub() {
+        let mock: Mock<i64, Reentrant> = Mock::new("AMockName".to_string());
+        mock.when(0).will_return(Reentrant { mock: None });
+        mock.when(1).will_return(Reentrant {
+            mock: Some(mock.clone()),
+        });
+
+        mock.called(1);
+
+        assert!(verify(mock.was_called_with(0).times(1)));
+        assert!(verify(mock.was_called_with(1).times(1)));
+    }
+
+    /// An input calling the mock which receives it when compared or
+    /// formatted
+    #[derive(Clone)]
+    struct Probe {
+        id: i64,
+        mock: Option<Mock<Probe, i64>>,
+    }
+
+    impl Probe {
+        fn new(id: i64) -> Probe {
+            Probe { id, mock: None }
+        }
+
+        fn calling(id: i64, mock: &Mock<Probe, i64>) -> Probe {
+            Probe {
+                id,
+                mock: Some(mock.clone()),
+            }
+        }
+
+        fn call(&self) {
+            if let Some(mock) = &self.mock {
+                mock.called(Probe::new(0));
+            }
+        }
+    }
+
+    impl PartialEq for Probe {
+        fn eq(&self, other: &Probe) -> bool {
+            self.call();
+            other.call();
+            self.id == other.id
+        }
+    }
+
+    impl std::fmt::Debug for Probe {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.call();
+            write!(f, "Probe({})", self.id)
+        }
+    }
+
+    fn ids(mock: &Mock<Probe, i64>) -> Vec<i64> {
+        mock.calls().iter().map(|probe| probe.id).collect()
+    }
+
+    #[test]
+    fn mock_can_be_called_from_inside_the_comparison_of_its_input() {
+        let mock: Mock<Probe, i64> = Mock::new("AMockName".to_string());
+        mock.when(Probe::new(0)).will_return(0);
+        mock.when(Probe::new(1)).will_return(1);
+
+        assert_eq!(mock.called(Probe::calling(1, &mock)), 1);
+
+        // The input was compared with both rules
+        assert_eq!(ids(&mock), vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn mock_can_be_called_from_inside_the_formatting_of_its_input() {
+        let mock: Mock<Probe, i64> = Mock::new("AMockName".to_string());
+        mock.when(Probe::new(0)).will_return(0);
+
+        let result = std::panic::catch_unwind(|| mock.called(Probe::calling(2, &mock)));
+
+        let failure = result.expect_err("no rule matches the input");
+        let message = failure.downcast_ref::<String>().unwrap();
+        assert!(
+            message.contains("unexpected input: \"Probe(2)\""),
+            "{}",
+            message
         );
+        // The input was compared with the rule, then formatted
+        assert_eq!(ids(&mock), vec![0, 0, 2]);
+    }
+
+    #[test]
+    fn mock_called_while_its_calls_are_verified_panics() {
+        let mock: Mock<Probe, i64> = Mock::new("AMockName".to_string());
+        mock.when(Probe::new(0)).will_return(0);
+        mock.called(Probe::calling(0, &mock));
+
+        let result = std::panic::catch_unwind(|| verify(mock.was_called_with(Probe::new(1))));
+
+        let failure = result.expect_err("the call can't be recorded");
+        let message = failure.downcast_ref::<&str>().unwrap();
+        assert!(message.contains("while it was using them"), "{}", message);
+    }
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn mock_is_send_when_its_output_is_not_sync() {
+        assert_send::<Mock<i32, std::cell::Cell<u8>>>();
+    }
+
+    #[test]
+    fn indexed_mock_output() {
+        let mock: Mock<Matcher<i64>, String> = Mock::indexed("AMockName".to_string());
+        mock.when(eq(65)).will_return(String::from("65"));
+        mock.when(eq(63)).will_return(String::from("63"));
+        mock.when(eq(65)).will_return(String::from("new 65"));
+
+        assert_eq!("new 65", mock.called(eq(65)));
+        assert_eq!("63", mock.called(eq(63)));
+
+        // `any` replaces the first rule and takes precedence over the next
+        // ones, as with a mock scanning its rules
+        mock.when(any()).will_return(String::from("any"));
+
+        assert_eq!("any", mock.called(eq(65)));
+        assert_eq!("any", mock.called(eq(63)));
+        assert_eq!("any", mock.called(eq(-1)));
+        assert!(verify(mock.was_called_with(eq(63)).times(2)));
+    }
+
+    #[test]
+    fn mock_with_bounded_history_keeps_the_last_calls() {
+        let mock: Mock<i64, String> =
+            Mock::new("AMockName".to_string()).with_history(History::Last(2));
+        mock.when(65).will_return(String::from("65"));
+        mock.when(63).will_return(String::from("63"));
+
+        mock.called(65);
+        mock.called(63);
+        mock.called(63);
+
+        assert_eq!(mock.calls(), vec![63, 63]);
+        assert_eq!(mock.call_count(), 3);
+        assert_eq!(mock.nth_call(0), None);
+        assert!(verify(mock.was_called_with(63)));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Can't verify calls with 63: 3 of 3 calls were discarded by the mock history"
+    )]
+    fn mock_with_discarded_history_should_panic_on_times() {
+        let mock: Mock<i64, String> =
+            Mock::new("AMockName".to_string()).with_history(History::CountOnly);
+        mock.when(63).will_return(String::from("63"));
+
+        mock.called(63);
+        mock.called(63);
+        mock.called(63);
+
+        assert_eq!(mock.call_count(), 3);
+        verify(mock.was_called_with(63).times(3));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Mock \"AMockName\" can't verify its interactions: 1 of 2 calls were discarded by its history"
+    )]
+    fn mock_with_discarded_history_should_panic_on_no_more_interactions() {
+        let mock: Mock<i64, String> =
+            Mock::new("AMockName".to_string()).with_history(History::Last(1));
+        mock.when(63).will_return(String::from("63"));
+
+        mock.called(63);
+        mock.called(63);
+
+        mock.verify_no_more_interactions();
+    }
+
+    fn apply(callback: impl Fn(i64) -> String, input: i64) -> String {
+        callback(input)
+    }
+
+    fn apply_boxed(mut callback: Box<dyn FnMut(i64) -> String + Send>, input: i64) -> String {
+        callback(input)
+    }
+
+    #[test]
+    fn mock_as_closure() {
+        let mock: Mock<i64, String> = Mock::new("AMockName".to_string());
+        mock.when(65).will_return(String::from("65"));
+        mock.when(63).will_return(String::from("63"));
+
+        assert_eq!("65", apply(mock.as_fn(), 65));
+        assert_eq!("63", apply_boxed(Box::new(mock.as_fn_mut()), 63));
+
+        assert!(verify(mock.was_called_with(65).times(1)));
+        assert!(verify(mock.was_called_with(63).times(1)));
+    }
+
+    #[test]
+    fn mock_records_outcomes() {
+        let mock = MyMock::new();
+        mock.int_to_string.when(65).will_return(String::from("65"));
+
+        mock.int_to_string(65);
+
+        let record = mock.int_to_string.nth_call_record(0).unwrap();
         assert_eq!(
-            false,
-            verify(mock.int_to_string.was_called_with(65).times(6))
+            record.outcome,
+            Outcome::Returned {
+                output: String::from("65"),
+                rule: 0
+            }
         );
+        assert_eq!(mock.int_to_string.nth_call_record(1), None);
+    }
+
+    #[test]
+    fn mock_returns_borrowed_outputs() {
+        let mock: Mock<i64, Arc<str>> = Mock::new("AMockName".to_string());
+        mock.when(65).will_return(Arc::from("65"));
+
+        let output: &str = mock.called_ref(65);
+        mock.when(65).will_return(Arc::from("other"));
+
+        assert_eq!(output, "65");
+        assert_eq!(&**mock.called_ref(65), "other");
+        assert_eq!(&**mock.called_ref(65), "other");
     }
 }
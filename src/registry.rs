@@ -0,0 +1,269 @@
+use crate::mock::Mock;
+use crate::sync::lock;
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// Mocks are registered by type, module of the mocked trait and name, so
+/// mocks of traits with the same name in different modules are distinct
+type Key = (TypeId, &'static str, String);
+
+/// The mocks of functions without a receiver, shared by the threads and tasks
+/// running a test.
+///
+/// Without one, those mocks are kept per thread, until the thread exits or
+/// `reset_per_thread_mocks` is called. Enter a fresh `StaticMocks` at the
+/// start of each test to isolate the test from the other tests run on the
+/// same thread, and run its tasks in `scope` so that they use the same mocks
+/// on any worker thread:
+///
+/// ```
+/// use mock_it::StaticMocks;
+///
+/// # async fn test() {
+/// let mocks = StaticMocks::new();
+/// let _entered = mocks.enter();
+/// tokio::spawn(mocks.scope(async {
+///     // Uses the same static mocks as the test
+/// }))
+/// .await
+/// .unwrap();
+/// # }
+/// ```
+///
+/// The mocks are dropped with the last clone of the `StaticMocks`, failing on
+/// unmet expectations like any other mock. Per-thread mocks are dropped when
+/// their thread exits, too late to fail a test, so they can't verify
+/// expectations on drop. `LocalMock` based mocks aren't `Send`, so they are
+/// always kept per thread.
+#[derive(Clone, Default)]
+pub struct StaticMocks {
+    mocks: Arc<Mutex<HashMap<Key, Box<dyn Any + Send>>>>,
+}
+
+/// Keeps a `StaticMocks` entered on the current thread until dropped
+pub struct Entered {
+    /// Identifies the entry of the guard, so guards dropped out of order
+    /// leave the other entries
+    id: usize,
+    // Entered mocks are a stack of the current thread
+    _not_send: PhantomData<*const ()>,
+}
+
+/// A future using a `StaticMocks` whenever it is polled
+pub struct Scoped<F> {
+    mocks: StaticMocks,
+    future: Pin<Box<F>>,
+}
+
+/// The mocks kept for a thread, dropped when the thread exits
+#[derive(Default)]
+struct ThreadMocks {
+    mocks: HashMap<Key, Box<dyn Any>>,
+}
+
+/// Numbers the entries of `StaticMocks`
+static ENTRIES: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static ENTERED: RefCell<Vec<(usize, StaticMocks)>> = const { RefCell::new(Vec::new()) };
+    static MOCKS: RefCell<ThreadMocks> = RefCell::new(ThreadMocks::default());
+}
+
+impl StaticMocks {
+    pub fn new() -> StaticMocks {
+        StaticMocks::default()
+    }
+
+    /// Use these mocks on the current thread until the guard is dropped
+    pub fn enter(&self) -> Entered {
+        let id = ENTRIES.fetch_add(1, Ordering::Relaxed);
+        ENTERED.with(|entered| entered.borrow_mut().push((id, self.clone())));
+        Entered {
+            id,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Use these mocks whenever the future is polled, on any thread
+    pub fn scope<F: Future>(&self, future: F) -> Scoped<F> {
+        Scoped {
+            mocks: self.clone(),
+            future: Box::pin(future),
+        }
+    }
+
+    fn get<M: Clone + Send + 'static>(&self, key: Key, new: impl FnOnce(String) -> M) -> M {
+        let mut mocks = lock(&self.mocks);
+        let name = key.2.clone();
+        let mock = mocks.entry(key).or_insert_with(|| Box::new(new(name)));
+
+        mock.downcast_ref::<M>()
+            .expect("Mocks are registered by type")
+            .clone()
+    }
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        // Drop the mocks outside of the stack, as dropping them can panic
+        let mocks = ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            let position = entered.iter().rposition(|(id, _mocks)| *id == self.id);
+            position.map(|position| entered.remove(position))
+        });
+        drop(mocks);
+    }
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let _entered = self.mocks.enter();
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// The mock registered under the module and name in the entered
+/// `StaticMocks`, or for the current thread, created on first use
+pub(crate) fn registered<I: Send + 'static, O: Send + 'static>(
+    module: &'static str,
+    name: String,
+    new: impl FnOnce(String) -> Mock<I, O>,
+) -> Mock<I, O> {
+    let key = (TypeId::of::<Mock<I, O>>(), module, name);
+    let entered = ENTERED.with(|entered| {
+        let entered = entered.borrow();
+        entered.last().map(|(_id, mocks)| mocks.clone())
+    });
+    match entered {
+        Some(mocks) => mocks.get(key, new),
+        None => per_thread(key, |name| new(name).kept_per_thread()),
+    }
+}
+
+/// The mock registered under the module and name for the current thread,
+/// created on first use
+pub(crate) fn thread_local<M: Clone + 'static>(
+    module: &'static str,
+    name: String,
+    new: impl FnOnce(String) -> M,
+) -> M {
+    per_thread((TypeId::of::<M>(), module, name), new)
+}
+
+fn per_thread<M: Clone + 'static>(key: Key, new: impl FnOnce(String) -> M) -> M {
+    MOCKS.with(|mocks| {
+        let mut mocks = mocks.borrow_mut();
+        let name = key.2.clone();
+        let mock = mocks
+            .mocks
+            .entry(key)
+            .or_insert_with(|| Box::new(new(name)));
+
+        mock.downcast_ref::<M>()
+            .expect("Mocks are registered by type")
+            .clone()
+    })
+}
+
+/// Forget the per-thread mocks of the current thread, so the next test run on
+/// it starts from fresh mocks
+pub fn reset_per_thread_mocks() {
+    // Drop the mocks outside of the registry, as dropping them can panic
+    let mocks = MOCKS.with(|mocks| std::mem::take(&mut mocks.borrow_mut().mocks));
+    drop(mocks);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mock<O: Send + 'static>(module: &'static str, name: &str) -> Mock<i32, O> {
+        registered(module, name.to_string(), Mock::new)
+    }
+
+    #[test]
+    fn per_thread_mocks_are_shared_by_module_name_and_type() {
+        let a: Mock<i32, i32> = mock("m", "a");
+        a.when(1).will_return(2);
+
+        assert_eq!(mock::<i32>("m", "a").called(1), 2);
+        assert_eq!(mock::<i32>("m", "b").call_count(), 0);
+        assert_eq!(mock::<i32>("other", "a").call_count(), 0);
+        assert_eq!(mock::<u8>("m", "a").call_count(), 0);
+    }
+
+    #[test]
+    fn per_thread_mocks_are_isolated_between_threads() {
+        let a: Mock<i32, i32> = mock("m", "a");
+        a.when(1).will_return(2);
+        a.called(1);
+
+        let calls_on_other_thread = std::thread::spawn(|| mock::<i32>("m", "a").call_count())
+            .join()
+            .unwrap();
+
+        assert_eq!(calls_on_other_thread, 0);
+    }
+
+    #[test]
+    fn reset_forgets_the_mocks() {
+        let a: Mock<i32, i32> = mock("m", "a");
+        a.when(1).will_return(2);
+        a.called(1);
+
+        reset_per_thread_mocks();
+
+        assert_eq!(mock::<i32>("m", "a").call_count(), 0);
+    }
+
+    #[test]
+    fn entered_mocks_are_shared_between_threads() {
+        let mocks = StaticMocks::new();
+        let entered = mocks.enter();
+        mock::<i32>("m", "a").when(1).will_return(2);
+
+        let other = mocks.clone();
+        let answer = std::thread::spawn(move || {
+            let _entered = other.enter();
+            mock::<i32>("m", "a").called(1)
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(answer, 2);
+        assert_eq!(mock::<i32>("m", "a").call_count(), 1);
+
+        drop(entered);
+        assert_eq!(mock::<i32>("m", "a").call_count(), 0);
+    }
+
+    #[test]
+    fn guards_dropped_out_of_order_leave_the_other_mocks_entered() {
+        let outer = StaticMocks::new();
+        let inner = StaticMocks::new();
+        let outer_entered = outer.enter();
+        let inner_entered = inner.enter();
+        mock::<i32>("m", "a").when(1).will_return(2);
+
+        drop(outer_entered);
+        assert_eq!(mock::<i32>("m", "a").called(1), 2);
+
+        drop(inner_entered);
+        assert_eq!(mock::<i32>("m", "a").call_count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Mock \"a\" is kept for its thread")]
+    fn per_thread_mocks_can_not_verify_on_drop() {
+        mock::<i32>("m", "a").was_called_with(1).verify_on_drop();
+    }
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+/// Outputs which share their value between clones, such as `Arc`, so a mock
+/// can tell the clones of a configured output apart from a new output
+pub trait SharedOutput {
+    /// Whether both outputs share the same value
+    fn same(&self, other: &Self) -> bool;
+}
+
+impl<T: ?Sized> SharedOutput for Arc<T> {
+    fn same(&self, other: &Self) -> bool {
+        Arc::ptr_eq(self, other)
+    }
+}
+
+impl<T: ?Sized> SharedOutput for Option<Arc<T>> {
+    fn same(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Some(output), Some(other)) => Arc::ptr_eq(output, other),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Outputs a mock handed out by reference. They are never removed, so each
+/// one lives as long as the mock which returned a reference to it, but a
+/// single clone is kept per configured output.
+#[derive(Debug)]
+pub(crate) struct Retained<O> {
+    outputs: Vec<Box<O>>,
+}
+
+impl<O> Default for Retained<O> {
+    fn default() -> Self {
+        Retained {
+            outputs: Vec::new(),
+        }
+    }
+}
+
+impl<O> Retained<O> {
+    /// Keep the output. The kept output stays at the same address until
+    /// `self` is dropped, so the mock can lend it for as long as itself.
+    pub(crate) fn keep(&mut self, output: O) -> *const O {
+        self.outputs.push(Box::new(output));
+        &**self.outputs.last().expect("the output was just pushed")
+    }
+
+    /// Number of outputs kept
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.outputs.len()
+    }
+}
+
+impl<O: SharedOutput> Retained<O> {
+    /// The kept clone of the output, if any
+    pub(crate) fn find(&self, output: &O) -> Option<*const O> {
+        self.outputs
+            .iter()
+            .find(|kept| kept.same(output))
+            .map(|kept| &**kept as *const O)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn retain<O: SharedOutput>(retained: &mut Retained<O>, output: O) -> *const O {
+        match retained.find(&output) {
+            Some(kept) => kept,
+            None => retained.keep(output),
+        }
+    }
+
+    #[test]
+    fn retained_outputs_outlive_later_ones() {
+        let mut retained = Retained::default();
+        let first = retain(&mut retained, Arc::new(String::from("first")));
+        for index in 0..100 {
+            retain(&mut retained, Arc::new(index.to_string()));
+        }
+
+        // SAFETY: `retained` is still alive
+        assert_eq!(unsafe { &**first }, "first");
+    }
+
+    #[test]
+    fn clones_of_a_retained_output_are_kept_once() {
+        let mut retained = Retained::default();
+        let output = Arc::new(String::from("output"));
+        let first = retain(&mut retained, output.clone());
+        for _ in 0..100 {
+            retain(&mut retained, output.clone());
+        }
+
+        assert_eq!(retained.len(), 1);
+        assert_eq!(first, retain(&mut retained, output));
+    }
+}
@@ -1,11 +1,262 @@
-#[derive(Debug, PartialEq)]
-pub struct Rule<I, O> {
-    pub input: I,
-    pub output: O,
+use crate::key::{key, Key};
+use crate::storage::Storage;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+
+/// Rules shared between the clones of a mock
+pub(crate) type Rules<S, I, O> = <S as Storage>::Shared<RuleTable<S, I, O>>;
+
+/// An output configured for an input. The output is shared on its own, so it
+/// can be cloned without holding the rules, while the rule itself is only
+/// reached through its table, so a mock only needs its inputs and outputs to
+/// be `Send`. Hits are counted while the rules are only read, as answering
+/// a call compares its input with user code.
+pub(crate) struct Rule<S: Storage, I, O> {
+    pub(crate) input: I,
+    pub(crate) output: S::Shared<O>,
+    hits: Cell<usize>,
+}
+
+impl<S: Storage, I, O> Rule<S, I, O> {
+    pub(crate) fn new(input: I, output: O) -> Rule<S, I, O> {
+        Rule {
+            input,
+            output: S::share(output),
+            hits: Cell::new(0),
+        }
+    }
+
+    pub(crate) fn hits(&self) -> usize {
+        self.hits.get()
+    }
+}
+
+impl<S: Storage, I: PartialEq, O: PartialEq> PartialEq for Rule<S, I, O> {
+    fn eq(&self, other: &Rule<S, I, O>) -> bool {
+        self.input == other.input
+            && S::read(&self.output, |output| {
+                S::read(&other.output, |other| output == other)
+            })
+            && self.hits == other.hits
+    }
+}
+
+impl<S: Storage, I: Debug, O: Debug> Debug for Rule<S, I, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rule")
+            .field("input", &self.input)
+            .field("output", S::debug(&self.output))
+            .field("hits", &self.hits.get())
+            .finish()
+    }
+}
+
+/// Rules in the order they were configured, optionally indexed by the hash of
+/// their input
+pub(crate) struct RuleTable<S: Storage, I, O> {
+    rules: Vec<Rule<S, I, O>>,
+    index: Option<Index<I>>,
+}
+
+#[derive(Debug)]
+struct Index<I> {
+    key: fn(&I) -> Option<u64>,
+    keys: Vec<Option<u64>>,
+    exact: HashMap<u64, Vec<usize>>,
+    inexact: Vec<usize>,
+}
+
+impl<S: Storage, I, O> RuleTable<S, I, O> {
+    pub(crate) fn new() -> RuleTable<S, I, O> {
+        RuleTable {
+            rules: Vec::new(),
+            index: None,
+        }
+    }
+
+    pub(crate) fn rules(&self) -> &[Rule<S, I, O>] {
+        &self.rules
+    }
+
+    /// The function computing the index key of an input, if the table is
+    /// indexed. It is handed out so user `Hash` code runs without the lock.
+    pub(crate) fn key_fn(&self) -> Option<fn(&I) -> Option<u64>> {
+        self.index.as_ref().map(|index| index.key)
+    }
+
+    /// Replace the rule at the position, or add it when there is none,
+    /// handing out the replaced rule to be dropped once the table is unlocked
+    pub(crate) fn set(
+        &mut self,
+        position: Option<usize>,
+        rule: Rule<S, I, O>,
+        key: Option<u64>,
+    ) -> Option<Rule<S, I, O>> {
+        let (position, replaced) = match position {
+            Some(position) => {
+                let replaced = std::mem::replace(&mut self.rules[position], rule);
+                (position, Some(replaced))
+            }
+            None => {
+                self.rules.push(rule);
+                (self.rules.len() - 1, None)
+            }
+        };
+
+        if let Some(index) = &mut self.index {
+            index.set(position, key);
+        }
+        replaced
+    }
+}
+
+impl<S: Storage, I: Debug, O: Debug> Debug for RuleTable<S, I, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(&self.rules).finish()
+    }
+}
+
+impl<S: Storage, I: PartialEq, O> RuleTable<S, I, O> {
+    /// The first rule matching the input with the key, in configuration
+    /// order, with its position. The rule is counted as used, and its output
+    /// is handed out to be cloned on its own.
+    pub(crate) fn answer(&self, input: &I, key: Option<u64>) -> Option<(usize, S::Shared<O>)> {
+        let position = self.position(input, key)?;
+        let rule = &self.rules[position];
+        rule.hits.set(rule.hits.get() + 1);
+
+        Some((position, rule.output.clone()))
+    }
+
+    /// The position of the rule matching the input with the key, if any
+    pub(crate) fn position(&self, input: &I, key: Option<u64>) -> Option<usize> {
+        let matches = |position: &usize| self.rules[*position].input == *input;
+        match (&self.index, key) {
+            (Some(index), Some(key)) => index.candidates(key).into_iter().find(matches),
+            _ => (0..self.rules.len()).find(matches),
+        }
+    }
+}
+
+/// Replace the rule matching the input, or add it when there is none. The
+/// input is hashed without holding the rules, then the rule is looked up and
+/// stored without another thread using the rules in between, so concurrent
+/// calls can't both add a rule for the same input.
+pub(crate) fn configure<S: Storage, I: PartialEq, O>(rules: &Rules<S, I, O>, input: I, output: O) {
+    let key = S::read(rules, |rules| rules.key_fn()).and_then(|key| key(&input));
+    let rule = Rule::new(input, output);
+    let replaced = S::hold(rules, || {
+        let position = S::read(rules, |rules| rules.position(&rule.input, key));
+        S::with(rules, |rules| rules.set(position, rule, key))
+    });
+
+    // Dropping the replaced rule runs user code, so it happens once the rules
+    // are released
+    drop(replaced);
+}
+
+impl<S: Storage, I: Key, O> RuleTable<S, I, O> {
+    pub(crate) fn indexed() -> RuleTable<S, I, O> {
+        RuleTable {
+            rules: Vec::new(),
+            index: Some(Index {
+                key: key::<I>,
+                keys: Vec::new(),
+                exact: HashMap::new(),
+                inexact: Vec::new(),
+            }),
+        }
+    }
 }
 
-impl<I, O> Rule<I, O> {
-    pub fn new(input: I, output: O) -> Rule<I, O> {
-        Rule { input, output }
+impl<I> Index<I> {
+    fn candidates(&self, key: u64) -> Vec<usize> {
+        let mut positions = self.inexact.clone();
+        if let Some(exact) = self.exact.get(&key) {
+            positions.extend(exact);
+        }
+        positions.sort_unstable();
+
+        positions
+    }
+
+    fn set(&mut self, position: usize, key: Option<u64>) {
+        if position == self.keys.len() {
+            self.keys.push(key);
+        } else {
+            let old_key = std::mem::replace(&mut self.keys[position], key);
+            self.positions(old_key).retain(|other| *other != position);
+        }
+
+        let positions = self.positions(key);
+        positions.push(position);
+        positions.sort_unstable();
+    }
+
+    fn positions(&mut self, key: Option<u64>) -> &mut Vec<usize> {
+        match key {
+            Some(key) => self.exact.entry(key).or_default(),
+            None => &mut self.inexact,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matcher::{any, eq, Matcher};
+    use crate::storage::Local;
+
+    type Table = RuleTable<Local, Matcher<i32>, &'static str>;
+
+    fn answer(table: &Table, input: i32) -> Option<&'static str> {
+        table
+            .answer(&eq(input), key(&eq(input)))
+            .map(|(_position, output)| *output.borrow())
+    }
+
+    fn set(table: &mut Table, input: Matcher<i32>, output: &'static str) {
+        let key = table.key_fn().and_then(|key| key(&input));
+        let position = table.position(&input, key);
+        table.set(position, Rule::new(input, output), key);
+    }
+
+    #[test]
+    fn indexed_rules_keep_configuration_order() {
+        let mut table = RuleTable::indexed();
+        set(&mut table, eq(1), "one");
+        set(&mut table, eq(2), "two");
+
+        assert_eq!(answer(&table, 2), Some("two"));
+        assert_eq!(answer(&table, 3), None);
+
+        // `any` replaces the first rule, and comes before the second one
+        set(&mut table, any(), "any");
+
+        assert_eq!(answer(&table, 2), Some("any"));
+        assert_eq!(answer(&table, 3), Some("any"));
+    }
+
+    #[test]
+    fn indexed_rules_follow_replaced_rules() {
+        let mut table = RuleTable::indexed();
+        set(&mut table, eq(1), "one");
+        set(&mut table, any(), "any");
+
+        assert_eq!(answer(&table, 1), Some("any"));
+        assert_eq!(answer(&table, 2), Some("any"));
+        assert_eq!(table.rules().len(), 1);
+    }
+
+    #[test]
+    fn unindexed_rules_are_scanned() {
+        let mut table = RuleTable::new();
+        set(&mut table, eq(1), "one");
+        set(&mut table, eq(2), "two");
+
+        assert_eq!(answer(&table, 2), Some("two"));
+        assert_eq!(answer(&table, 3), None);
+        assert_eq!(table.rules()[1].hits(), 1);
     }
 }
@@ -0,0 +1,53 @@
+use crate::sync::lock;
+use std::sync::{Condvar, Mutex, PoisonError};
+use std::task::Waker;
+use std::time::Instant;
+
+/// Wakes up the threads and tasks waiting for a mock to be called
+#[derive(Debug, Default)]
+pub(crate) struct Signal {
+    /// Number of notifications so far
+    notified: Mutex<u64>,
+    condvar: Condvar,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl Signal {
+    pub(crate) fn notify(&self) {
+        *lock(&self.notified) += 1;
+        self.condvar.notify_all();
+
+        let wakers = std::mem::take(&mut *lock(&self.wakers));
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Number of notifications so far, to wait for the next ones
+    pub(crate) fn notified(&self) -> u64 {
+        *lock(&self.notified)
+    }
+
+    /// Block until notified more than `seen` times or until the deadline is
+    /// reached, whichever comes first
+    pub(crate) fn wait_until(&self, seen: u64, deadline: Instant) {
+        let mut notified = lock(&self.notified);
+        while *notified == seen {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                return;
+            }
+            notified = self
+                .condvar
+                .wait_timeout(notified, timeout)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+    }
+
+    /// Wake the task on the next notification
+    pub(crate) fn register(&self, waker: &Waker) {
+        let mut wakers = lock(&self.wakers);
+        if !wakers.iter().any(|registered| registered.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+}
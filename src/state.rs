@@ -0,0 +1,284 @@
+use crate::arguments::{unexpected_input_message, ArgumentNames, Arguments};
+use crate::call::{Call, CallLog, History, Outcome};
+use crate::key::Key;
+use crate::retained::{Retained, SharedOutput};
+use crate::rule::{RuleTable, Rules};
+use crate::storage::Storage;
+use crate::sync::{panic_message, poison_note};
+use std::fmt::{self, Debug};
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+
+/// The state shared by the clones of a mock, and what calling, inspecting and
+/// verifying the mock does with it, for both `Mock` and `LocalMock`
+pub(crate) struct MockState<S: Storage, I, O> {
+    pub(crate) name: String,
+    pub(crate) calls: S::Shared<CallLog<I, O>>,
+    pub(crate) rules: Rules<S, I, O>,
+    arguments: Option<S::Shared<ArgumentNames<I>>>,
+    retained: S::Shared<Retained<O>>,
+}
+
+impl<S: Storage, I, O> Clone for MockState<S, I, O> {
+    fn clone(&self) -> MockState<S, I, O> {
+        MockState {
+            name: self.name.clone(),
+            calls: self.calls.clone(),
+            rules: self.rules.clone(),
+            arguments: self.arguments.clone(),
+            retained: self.retained.clone(),
+        }
+    }
+}
+
+impl<S: Storage, I: Debug, O: Debug> Debug for MockState<S, I, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockState")
+            .field("name", &self.name)
+            .field("calls", S::debug(&self.calls))
+            .field("rules", S::debug(&self.rules))
+            .finish()
+    }
+}
+
+impl<S: Storage, I, O> MockState<S, I, O> {
+    pub(crate) fn new(name: String) -> MockState<S, I, O> {
+        MockState {
+            name,
+            calls: S::share(CallLog::default()),
+            rules: S::share(RuleTable::new()),
+            arguments: None,
+            retained: S::share(Retained::default()),
+        }
+    }
+
+    pub(crate) fn set_history(&self, history: History) {
+        let discarded = S::with(&self.calls, |calls| calls.set_history(history));
+        drop(discarded);
+    }
+
+    pub(crate) fn call_count(&self) -> usize {
+        S::read(&self.calls, |calls| calls.len())
+    }
+
+    /// The note telling which panic poisoned the state since the last failure
+    /// of the mock, if any, so the failure mentions that the state may be
+    /// inconsistent
+    fn poisoning(&self) -> String {
+        let outputs: Vec<Option<String>> = S::read(&self.rules, |rules| {
+            rules
+                .rules()
+                .iter()
+                .map(|rule| S::take_poison(&rule.output))
+                .collect()
+        });
+        let arguments = self.arguments.as_ref().and_then(S::take_poison);
+        let panics = [
+            S::take_poison(&self.calls),
+            S::take_poison(&self.rules),
+            S::take_poison(&self.retained),
+            arguments,
+        ];
+
+        match panics.into_iter().chain(outputs).flatten().next() {
+            Some(panic) => poison_note(&panic),
+            None => String::new(),
+        }
+    }
+}
+
+impl<S: Storage, I: Arguments, O> MockState<S, I, O> {
+    pub(crate) fn with_arg_names(self, names: &[&str]) -> MockState<S, I, O> {
+        MockState {
+            arguments: Some(S::share(ArgumentNames::new(names))),
+            ..self
+        }
+    }
+}
+
+impl<S: Storage, I: Key, O> MockState<S, I, O> {
+    pub(crate) fn indexed(name: String) -> MockState<S, I, O> {
+        MockState {
+            calls: S::share(CallLog::indexed(History::default())),
+            rules: S::share(RuleTable::indexed()),
+            ..MockState::new(name)
+        }
+    }
+}
+
+impl<S: Storage, I: PartialEq + Debug, O: Clone> MockState<S, I, O> {
+    /// Answer the call with the output of the first matching rule, recording
+    /// it, then let the mock know a call was recorded
+    #[track_caller]
+    pub(crate) fn called(&self, input: I, recorded: impl FnOnce()) -> O {
+        // Get the when value for this input. The inputs are compared and the
+        // output is cloned while the rules are only read, so the mock can be
+        // called again from inside `PartialEq` or `Clone`.
+        let key = S::read(&self.rules, |rules| rules.key_fn()).and_then(|key| key(&input));
+        let rule = S::read(&self.rules, |rules| rules.answer(&input, key));
+        // A panicking `Clone` is recorded like any other outcome
+        let when_value = rule.map(|(position, output)| {
+            let outputs = catch_unwind(AssertUnwindSafe(|| {
+                let output = S::read(&output, |output| output.clone());
+                let recorded = output.clone();
+                (output, recorded)
+            }));
+            (position, outputs)
+        });
+
+        // Record this call with its outcome, then return the when value or
+        // fail if there is no when value. No lock is held when failing, so the
+        // failure doesn't poison the mock for later calls. Calls are built
+        // outside of the closures, which don't track the caller.
+        match when_value {
+            Some((rule, Ok((output, recorded_output)))) => {
+                let outcome = Outcome::Returned {
+                    output: recorded_output,
+                    rule,
+                };
+                let call = Call::new(input, outcome);
+                self.record(call, key);
+                recorded();
+                output
+            }
+            Some((_rule, Err(payload))) => {
+                let outcome = Outcome::Panicked(panic_message(&*payload));
+                let call = Call::new(input, outcome);
+                self.record(call, key);
+                recorded();
+                resume_unwind(payload)
+            }
+            None => {
+                let message = S::read(&self.rules, |rules| match &self.arguments {
+                    Some(arguments) => S::read(arguments, |arguments| {
+                        unexpected_input_message(&self.name, &input, rules.rules(), Some(arguments))
+                    }),
+                    None => unexpected_input_message(&self.name, &input, rules.rules(), None),
+                });
+                let message = message + &self.poisoning();
+                let outcome = Outcome::Panicked(message.clone());
+                let call = Call::new(input, outcome);
+                self.record(call, key);
+                recorded();
+                panic!("{}", message)
+            }
+        }
+    }
+
+    /// Record the call, dropping the call discarded by the history once the
+    /// calls are unlocked
+    fn record(&self, call: Call<I, O>, key: Option<u64>) {
+        let discarded = S::with(&self.calls, |calls| calls.record(call, key));
+        drop(discarded);
+    }
+}
+
+impl<S: Storage, I: PartialEq + Debug, O: Clone + SharedOutput> MockState<S, I, O> {
+    #[track_caller]
+    pub(crate) fn called_ref(&self, input: I, recorded: impl FnOnce()) -> &O {
+        let output = self.called(input, recorded);
+        // Only a single clone of each output is kept, and `SharedOutput` is
+        // user code, so the kept outputs are searched while only read
+        let (retained, unused) = S::hold(&self.retained, || {
+            match S::read(&self.retained, |retained| retained.find(&output)) {
+                Some(kept) => (kept, Some(output)),
+                None => (
+                    S::with(&self.retained, |retained| retained.keep(output)),
+                    None,
+                ),
+            }
+        });
+        drop(unused);
+
+        // SAFETY: the output is boxed and owned by the retained outputs of
+        // `self`, which never drop nor replace it before being dropped
+        unsafe { &*retained }
+    }
+}
+
+impl<S: Storage, I: Clone, O> MockState<S, I, O> {
+    pub(crate) fn calls(&self) -> Vec<I> {
+        S::read(&self.calls, |calls| {
+            calls.iter().map(|call| call.input.clone()).collect()
+        })
+    }
+
+    pub(crate) fn last_call(&self) -> Option<I> {
+        S::read(&self.calls, |calls| {
+            calls.last().map(|call| call.input.clone())
+        })
+    }
+
+    pub(crate) fn nth_call(&self, index: usize) -> Option<I> {
+        S::read(&self.calls, |calls| {
+            calls.get(index).map(|call| call.input.clone())
+        })
+    }
+}
+
+impl<S: Storage, I: Clone, O: Clone> MockState<S, I, O> {
+    pub(crate) fn call_records(&self) -> Vec<Call<I, O>> {
+        S::read(&self.calls, |calls| calls.iter().cloned().collect())
+    }
+
+    pub(crate) fn last_call_record(&self) -> Option<Call<I, O>> {
+        S::read(&self.calls, |calls| calls.last().cloned())
+    }
+
+    pub(crate) fn nth_call_record(&self, index: usize) -> Option<Call<I, O>> {
+        S::read(&self.calls, |calls| calls.get(index).cloned())
+    }
+}
+
+impl<S: Storage, I: Debug, O> MockState<S, I, O> {
+    pub(crate) fn verify_no_more_interactions(&self) {
+        // Build the failure while reading the calls, but fail without them
+        let failure = S::read(&self.calls, |calls| {
+            if calls.discarded() > 0 {
+                return Some(format!(
+                    "Mock \"{}\" can't verify its interactions: {} of {} calls were discarded by its history",
+                    self.name,
+                    calls.discarded(),
+                    calls.len()
+                ));
+            }
+
+            let unverified: Vec<String> = calls
+                .iter()
+                .filter(|call| !call.is_verified())
+                .map(Call::describe)
+                .collect();
+            match unverified.is_empty() {
+                true => None,
+                false => Some(format!(
+                    "Mock \"{}\" has unverified calls: {}",
+                    self.name,
+                    unverified.join(", ")
+                )),
+            }
+        });
+
+        if let Some(failure) = failure {
+            panic!("{}{}", failure, self.poisoning());
+        }
+    }
+
+    pub(crate) fn assert_all_stubs_used(&self) {
+        let unused: Vec<String> = S::read(&self.rules, |rules| {
+            rules
+                .rules()
+                .iter()
+                .filter(|rule| rule.hits() == 0)
+                .map(|rule| format!("{:?}", rule.input))
+                .collect()
+        });
+
+        if !unused.is_empty() {
+            panic!(
+                "Mock \"{}\" has unused stubs: {}{}",
+                self.name,
+                unused.join(", "),
+                self.poisoning()
+            );
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::sync::{borrow, borrow_mut, Reentrant};
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// How the state of a mock is shared between its clones. `Mock` locks it to
+/// be used from any thread, `LocalMock` only borrows it.
+///
+/// User code only runs while the state is read, never while it is written,
+/// so it can call the mock again.
+pub(crate) trait Storage {
+    type Shared<T>: Clone;
+
+    fn share<T>(value: T) -> Self::Shared<T>;
+
+    /// Run the closure with exclusive access to the shared value. The closure
+    /// must not run user code, nor drop user values.
+    fn with<T, R>(shared: &Self::Shared<T>, f: impl FnOnce(&mut T) -> R) -> R;
+
+    /// Run the closure with shared access to the shared value
+    fn read<T, R>(shared: &Self::Shared<T>, f: impl FnOnce(&T) -> R) -> R;
+
+    /// Run the closure without other threads using the shared value, so the
+    /// reads and writes it makes aren't interleaved with theirs
+    fn hold<T, R>(shared: &Self::Shared<T>, f: impl FnOnce() -> R) -> R;
+
+    fn debug<'a, T: Debug + 'a>(shared: &'a Self::Shared<T>) -> &'a (dyn Debug + 'a);
+
+    /// The panic which poisoned the shared value since it was last asked, if
+    /// any
+    fn take_poison<T>(shared: &Self::Shared<T>) -> Option<String>;
+}
+
+/// State behind `Arc<Reentrant<_>>`, for `Mock`
+#[derive(Debug)]
+pub(crate) struct Threaded;
+
+/// State behind `Rc<RefCell<_>>`, for `LocalMock`
+#[derive(Debug)]
+pub(crate) struct Local;
+
+impl Storage for Threaded {
+    type Shared<T> = Arc<Reentrant<T>>;
+
+    fn share<T>(value: T) -> Arc<Reentrant<T>> {
+        Arc::new(Reentrant::new(value))
+    }
+
+    fn with<T, R>(shared: &Arc<Reentrant<T>>, f: impl FnOnce(&mut T) -> R) -> R {
+        shared.write(f)
+    }
+
+    fn read<T, R>(shared: &Arc<Reentrant<T>>, f: impl FnOnce(&T) -> R) -> R {
+        shared.read(f)
+    }
+
+    fn hold<T, R>(shared: &Arc<Reentrant<T>>, f: impl FnOnce() -> R) -> R {
+        shared.hold(f)
+    }
+
+    fn debug<'a, T: Debug + 'a>(shared: &'a Arc<Reentrant<T>>) -> &'a (dyn Debug + 'a) {
+        shared
+    }
+
+    fn take_poison<T>(shared: &Arc<Reentrant<T>>) -> Option<String> {
+        shared.take_poison()
+    }
+}
+
+impl Storage for Local {
+    type Shared<T> = Rc<RefCell<T>>;
+
+    fn share<T>(value: T) -> Rc<RefCell<T>> {
+        Rc::new(RefCell::new(value))
+    }
+
+    fn with<T, R>(shared: &Rc<RefCell<T>>, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut borrow_mut(shared))
+    }
+
+    fn read<T, R>(shared: &Rc<RefCell<T>>, f: impl FnOnce(&T) -> R) -> R {
+        f(&borrow(shared))
+    }
+
+    fn hold<T, R>(_shared: &Rc<RefCell<T>>, f: impl FnOnce() -> R) -> R {
+        // Only the current thread uses the value
+        f()
+    }
+
+    fn debug<'a, T: Debug + 'a>(shared: &'a Rc<RefCell<T>>) -> &'a (dyn Debug + 'a) {
+        shared
+    }
+
+    fn take_poison<T>(_shared: &Rc<RefCell<T>>) -> Option<String> {
+        // A `RefCell` is never poisoned, and a panic while it is borrowed
+        // can only be observed by the thread which panicked
+        None
+    }
+}
@@ -0,0 +1,57 @@
+use crate::call::{CallLog, Calls};
+use crate::signal::Signal;
+use std::future::poll_fn;
+use std::sync::Arc;
+use std::task::Poll;
+
+/// Receives the calls made to a mock after the subscription was created.
+///
+/// Calls discarded by the history of the mock before being received are
+/// skipped.
+pub struct Subscription<I, O = ()> {
+    calls: Calls<I, O>,
+    signal: Arc<Signal>,
+    next: usize,
+}
+
+impl<I, O> Subscription<I, O> {
+    pub(crate) fn new(calls: Calls<I, O>, signal: Arc<Signal>) -> Subscription<I, O> {
+        let next = calls.read(CallLog::len);
+        Subscription {
+            calls,
+            signal,
+            next,
+        }
+    }
+}
+
+impl<I: Clone, O> Subscription<I, O> {
+    /// Input of the next call, if the mock was called since the last one
+    /// received
+    pub fn try_next(&mut self) -> Option<I> {
+        let next = &mut self.next;
+        self.calls.read(|calls| {
+            while *next < calls.len() {
+                *next += 1;
+                if let Some(call) = calls.get(*next - 1) {
+                    return Some(call.input.clone());
+                }
+            }
+
+            None
+        })
+    }
+
+    /// Wait for the next call and return its input
+    pub async fn next_call(&mut self) -> I {
+        poll_fn(|cx| {
+            // Register before checking, so a call made in between still wakes us
+            self.signal.register(cx.waker());
+            match self.try_next() {
+                Some(input) => Poll::Ready(input),
+                None => Poll::Pending,
+            }
+        })
+        .await
+    }
+}
@@ -0,0 +1,262 @@
+use std::any::Any;
+use std::cell::{Ref, RefCell, RefMut};
+use std::fmt::{self, Debug};
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe, RefUnwindSafe, UnwindSafe};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, ThreadId};
+
+/// Lock a mutex of the bookkeeping of the crate, which never runs user code
+/// while holding it, recovering it when a thread panicked while holding it
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The state of a mock shared between threads.
+///
+/// User code, such as the `PartialEq`, `Debug` and `Clone` implementations of
+/// inputs and outputs, runs while the state is read, and may use the mock
+/// again: the lock is re-entrant for the thread holding it, and reads can be
+/// nested. The state is only written by the crate, without running user
+/// code, so writing it from inside a read panics instead of deadlocking.
+///
+/// A panic while writing the state must only fail the test that caused it,
+/// not every later call on every clone of the mock, so the lock is never
+/// poisoned. The panic is still reported by the next failure of the mock, as
+/// it may have left the state half updated.
+pub(crate) struct Reentrant<T> {
+    /// The thread holding the lock, with how many times it took it
+    owner: Mutex<Option<(ThreadId, usize)>>,
+    released: Condvar,
+    value: RefCell<T>,
+    poisoned_by: Mutex<Option<String>>,
+}
+
+// SAFETY: the value is only reached by the thread holding the lock, which
+// hands it over to the next one like a `Mutex` does
+unsafe impl<T: Send> Sync for Reentrant<T> {}
+
+// A panic while writing the value is remembered instead of poisoning it, so
+// the value can be used across `catch_unwind` like a `Mutex`
+impl<T> UnwindSafe for Reentrant<T> {}
+impl<T> RefUnwindSafe for Reentrant<T> {}
+
+/// Releases the lock once the closure holding it returns or unwinds
+struct Held<'a, T>(&'a Reentrant<T>);
+
+impl<T> Reentrant<T> {
+    pub(crate) fn new(value: T) -> Reentrant<T> {
+        Reentrant {
+            owner: Mutex::new(None),
+            released: Condvar::new(),
+            value: RefCell::new(value),
+            poisoned_by: Mutex::new(None),
+        }
+    }
+
+    /// Run the closure while the current thread holds the lock, waiting for
+    /// other threads to release it, so the reads and writes it makes aren't
+    /// interleaved with theirs
+    pub(crate) fn hold<R>(&self, f: impl FnOnce() -> R) -> R {
+        let current = thread::current().id();
+        let mut owner = lock(&self.owner);
+        loop {
+            match &mut *owner {
+                None => *owner = Some((current, 1)),
+                Some((thread, depth)) if *thread == current => *depth += 1,
+                Some(_) => {
+                    owner = self
+                        .released
+                        .wait(owner)
+                        .unwrap_or_else(PoisonError::into_inner);
+                    continue;
+                }
+            }
+            break;
+        }
+        drop(owner);
+
+        let _held = Held(self);
+        f()
+    }
+
+    /// Run the closure with shared access to the value
+    pub(crate) fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        self.hold(|| f(&borrow(&self.value)))
+    }
+
+    /// Run the closure with exclusive access to the value, remembering the
+    /// message of a panic unwinding out of it
+    pub(crate) fn write<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.hold(|| {
+            let mut value = borrow_mut(&self.value);
+            match catch_unwind(AssertUnwindSafe(|| f(&mut value))) {
+                Ok(result) => result,
+                Err(payload) => {
+                    *lock(&self.poisoned_by) = Some(format!("'{}'", panic_message(&*payload)));
+                    drop(value);
+                    resume_unwind(payload)
+                }
+            }
+        })
+    }
+
+    /// The panic which poisoned the value since it was last asked, if any
+    pub(crate) fn take_poison(&self) -> Option<String> {
+        lock(&self.poisoned_by).take()
+    }
+}
+
+impl<T> Drop for Held<'_, T> {
+    fn drop(&mut self) {
+        let mut owner = lock(&self.0.owner);
+        if let Some((_thread, depth)) = &mut *owner {
+            *depth -= 1;
+            if *depth == 0 {
+                *owner = None;
+                self.0.released.notify_one();
+            }
+        }
+    }
+}
+
+impl<T: Default> Default for Reentrant<T> {
+    fn default() -> Reentrant<T> {
+        Reentrant::new(T::default())
+    }
+}
+
+impl<T: Debug> Debug for Reentrant<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.read(|value| value.fmt(f))
+    }
+}
+
+/// Borrow a value of a mock to read it. It is only written by the crate,
+/// which doesn't read it meanwhile.
+pub(crate) fn borrow<T>(value: &RefCell<T>) -> Ref<'_, T> {
+    value
+        .try_borrow()
+        .expect("The state of a mock isn't read while it is written")
+}
+
+/// Borrow a value of a mock to write it, failing when user code running
+/// while it is read tries to change it
+pub(crate) fn borrow_mut<T>(value: &RefCell<T>) -> RefMut<'_, T> {
+    value.try_borrow_mut().unwrap_or_else(|_| {
+        panic!(
+            "A mock was changed by the `PartialEq`, `Debug` or `Clone` implementation of its \
+             inputs or outputs, while it was using them"
+        )
+    })
+}
+
+/// The note added to a failure of a mock poisoned by the panic
+pub(crate) fn poison_note(panic: &str) -> String {
+    format!(
+        "\nThe mock was poisoned by a panic while it was locked, and may be inconsistent: {}",
+        panic
+    )
+}
+
+/// The message of a panic, from its payload
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match (
+        payload.downcast_ref::<&str>(),
+        payload.downcast_ref::<String>(),
+    ) {
+        (Some(message), _) => message.to_string(),
+        (None, Some(message)) => message.clone(),
+        (None, None) => "Box<dyn Any>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn panics_while_writing_poison_the_value() {
+        let value = Reentrant::new(0);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            value.write(|value| {
+                *value += 1;
+                panic!("halfway");
+            })
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(value.take_poison(), Some("'halfway'".to_string()));
+        assert_eq!(value.take_poison(), None);
+        assert_eq!(value.read(|value| *value), 1);
+    }
+
+    #[test]
+    fn writing_while_unwinding_does_not_poison() {
+        struct WriteOnDrop<'a>(&'a Reentrant<i32>);
+
+        impl Drop for WriteOnDrop<'_> {
+            fn drop(&mut self) {
+                self.0.write(|value| *value += 1);
+            }
+        }
+
+        let value = Reentrant::new(0);
+        let result = thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let _write_on_drop = WriteOnDrop(&value);
+                    panic!("unrelated");
+                })
+                .join()
+        });
+
+        assert!(result.is_err());
+        assert_eq!(value.read(|value| *value), 1);
+        assert_eq!(value.take_poison(), None);
+    }
+
+    #[test]
+    fn reads_nest_on_the_thread_holding_the_lock() {
+        let value = Reentrant::new(1);
+
+        let sum = value.read(|outer| value.read(|inner| outer + inner));
+
+        assert_eq!(sum, 2);
+    }
+
+    #[test]
+    fn writing_while_reading_panics() {
+        let value = Reentrant::new(1);
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            value.read(|_value| value.write(|value| *value += 1))
+        }));
+
+        let message = panic_message(&*result.unwrap_err());
+        assert!(message.contains("while it was using them"), "{}", message);
+        value.write(|value| *value += 1);
+        assert_eq!(value.read(|value| *value), 2);
+    }
+
+    #[test]
+    fn other_threads_wait_for_the_lock() {
+        let value = Reentrant::new(0);
+        let (held, hold) = mpsc::channel();
+
+        thread::scope(|scope| {
+            value.hold(|| {
+                scope.spawn(|| {
+                    held.send(()).unwrap();
+                    value.write(|value| *value += 1);
+                });
+                hold.recv().unwrap();
+                thread::sleep(Duration::from_millis(20));
+                assert_eq!(value.read(|value| *value), 0);
+            });
+        });
+
+        assert_eq!(value.read(|value| *value), 1);
+    }
+}
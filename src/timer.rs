@@ -0,0 +1,134 @@
+use crate::signal::Signal;
+use crate::sync::lock;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Condvar, Mutex, OnceLock, PoisonError, Weak};
+use std::time::Instant;
+
+/// Notifies signals at their deadline, from a single thread shared by every
+/// pending `Eventually`. The thread is started on first use, and sleeps until
+/// the earliest deadline.
+struct Timer {
+    deadlines: Mutex<Deadlines>,
+    /// Wakes the thread when an earlier deadline is registered
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct Deadlines {
+    next_id: u64,
+    pending: BTreeMap<(Instant, u64), Weak<Signal>>,
+}
+
+/// A deadline registered with the timer, cancelled when dropped
+pub(crate) struct Deadline {
+    key: (Instant, u64),
+}
+
+/// Notify the signal at the deadline, unless the returned guard was dropped
+/// before
+pub(crate) fn notify_at(signal: &Arc<Signal>, deadline: Instant) -> Deadline {
+    let timer = timer();
+    let mut deadlines = lock(&timer.deadlines);
+    let key = (deadline, deadlines.next_id);
+    deadlines.next_id += 1;
+
+    let earliest = deadlines
+        .pending
+        .keys()
+        .next()
+        .is_none_or(|first| key < *first);
+    deadlines.pending.insert(key, Arc::downgrade(signal));
+    if earliest {
+        timer.changed.notify_one();
+    }
+
+    Deadline { key }
+}
+
+impl Drop for Deadline {
+    fn drop(&mut self) {
+        lock(&timer().deadlines).pending.remove(&self.key);
+    }
+}
+
+fn timer() -> &'static Timer {
+    static TIMER: OnceLock<Timer> = OnceLock::new();
+    TIMER.get_or_init(|| {
+        std::thread::Builder::new()
+            .name("mock-it timer".to_string())
+            .spawn(|| run(timer()))
+            .expect("The timer thread can be spawned");
+        Timer {
+            deadlines: Mutex::new(Deadlines::default()),
+            changed: Condvar::new(),
+        }
+    })
+}
+
+fn run(timer: &Timer) {
+    let mut deadlines = lock(&timer.deadlines);
+    loop {
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        while let Some(entry) = deadlines.pending.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            expired.push(entry.remove());
+        }
+
+        // Wake the waiting tasks without holding the deadlines
+        if !expired.is_empty() {
+            drop(deadlines);
+            expired
+                .iter()
+                .filter_map(Weak::upgrade)
+                .for_each(|signal| signal.notify());
+            deadlines = lock(&timer.deadlines);
+            continue;
+        }
+
+        deadlines = match deadlines.pending.keys().next() {
+            Some((deadline, _id)) => {
+                let timeout = deadline.saturating_duration_since(now);
+                let waited = timer.changed.wait_timeout(deadlines, timeout);
+                waited.unwrap_or_else(PoisonError::into_inner).0
+            }
+            None => timer
+                .changed
+                .wait(deadlines)
+                .unwrap_or_else(PoisonError::into_inner),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn signals_are_notified_at_their_deadline() {
+        let signal = Arc::new(Signal::default());
+        let later = notify_at(&signal, Instant::now() + Duration::from_secs(60));
+        let _soon = notify_at(&signal, Instant::now() + Duration::from_millis(10));
+
+        signal.wait_until(0, Instant::now() + Duration::from_secs(5));
+
+        assert_eq!(signal.notified(), 1);
+        drop(later);
+    }
+
+    #[test]
+    fn cancelled_deadlines_are_not_notified() {
+        let signal = Arc::new(Signal::default());
+        drop(notify_at(
+            &signal,
+            Instant::now() + Duration::from_millis(10),
+        ));
+
+        signal.wait_until(0, Instant::now() + Duration::from_millis(50));
+
+        assert_eq!(signal.notified(), 0);
+    }
+}
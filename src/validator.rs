@@ -1,61 +1,344 @@
-use std::sync::Arc;
-use std::sync::Mutex;
+use crate::call::{Call, CallLog, Calls};
+use crate::eventually::Eventually;
+use crate::expectation::Expectations;
+use crate::signal::Signal;
+use crate::storage::{Storage, Threaded};
+use crate::sync::poison_note;
+use std::sync::Weak;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
-pub fn verify<I: PartialEq>(validator: Validator<I>) -> bool {
+pub fn verify<V: Verify>(validator: V) -> bool {
     validator.called()
 }
 
-pub struct Validator<I> {
-    calls: Arc<Mutex<Vec<I>>>,
+/// Expectations `verify` can check, built by `Mock::was_called_with` and
+/// `LocalMock::was_called_with`
+pub trait Verify {
+    /// Whether the expectation holds, marking the matching calls as verified
+    fn called(self) -> bool;
+}
+
+pub struct Validator<I, O = ()> {
+    check: Check<Threaded, I, O>,
+    expectations: Weak<Expectations>,
+    signal: Arc<Signal>,
+}
+
+/// An expectation on the calls of a mock, and how it is checked, for both
+/// `Validator` and `LocalValidator`
+pub(crate) struct Check<S: Storage, I, O> {
+    calls: Recorded<S, I, O>,
     result: Option<bool>,
     input: I,
+    /// The index key of the input, when the mock is indexed
+    key: Option<u64>,
+    times: Option<usize>,
 }
 
-impl<I> Validator<I> {
-    pub fn new(calls: Arc<Mutex<Vec<I>>>, input: I) -> Validator<I> {
-        Validator {
+/// Where a check finds the calls it verifies
+enum Recorded<S: Storage, I, O> {
+    /// The calls recorded by a mock
+    Log(S::Shared<CallLog<I, O>>),
+    /// Inputs recorded by hand, for validators built by `Validator::new`
+    Inputs(Arc<Mutex<Vec<I>>>),
+}
+
+impl<S: Storage, I, O> Check<S, I, O> {
+    pub(crate) fn new(calls: S::Shared<CallLog<I, O>>, input: I) -> Check<S, I, O> {
+        Check::recorded(Recorded::Log(calls), input)
+    }
+
+    fn recorded(calls: Recorded<S, I, O>, input: I) -> Check<S, I, O> {
+        // The input is hashed without holding the calls
+        let key = match &calls {
+            Recorded::Log(calls) => S::read(calls, |calls| calls.key_fn()),
+            Recorded::Inputs(_) => None,
+        };
+        Check {
+            key: key.and_then(|key| key(&input)),
             calls,
             result: None,
             input,
+            times: None,
         }
     }
 }
 
-impl<I: PartialEq> Validator<I> {
-    pub fn result(&mut self) -> bool {
+impl<S: Storage, I: PartialEq, O> Check<S, I, O> {
+    pub(crate) fn result(&mut self) -> bool {
         match self.result {
             Some(val) => val,
             None => {
-                let calls = self.calls.lock().unwrap();
-                let was_called = calls.iter().any(|value| value == &self.input);
+                let was_called = self.verify(false, |times_called| times_called > 0);
                 self.result = Some(was_called);
                 was_called
             }
         }
     }
-    pub fn times(mut self, times: usize) -> Validator<I> {
-        let times_called = {
-            let calls = self.calls.lock().unwrap();
-            calls.iter().filter(|value| *value == &self.input).count()
-        };
 
-        if times_called != times {
+    pub(crate) fn times(&mut self, times: usize) {
+        if !self.verify(true, |times_called| times_called == times) {
             self.result = Some(false)
         }
+        self.times = Some(times);
+    }
+
+    fn holds_for(&self, calls: &CallLog<I, O>) -> bool {
+        let times_called = calls.matching(&self.input, self.key).len();
+        match self.times {
+            Some(times) => times_called >= times,
+            None => times_called > 0,
+        }
+    }
+
+    /// Evaluate the expectation against the calls recorded so far
+    fn settle(&mut self) {
+        let result = match self.times {
+            Some(times) => self.verify(true, |times_called| times_called == times),
+            None => self.verify(false, |times_called| times_called > 0),
+        };
+        self.result = Some(result);
+    }
+
+    /// Whether the number of calls matching the input meets the expectation,
+    /// marking the calls as verified only when it does. Fails when the answer
+    /// depends on calls discarded by the history: always for an exact count,
+    /// otherwise only when no kept call matches. The calls are compared while
+    /// only read, then marked without another thread recording calls in
+    /// between.
+    fn verify(&self, exact: bool, meets: impl FnOnce(usize) -> bool) -> bool {
+        let input = &self.input;
+        let verified = match &self.calls {
+            Recorded::Log(calls) => S::hold(calls, || {
+                let matching = S::read(calls, |calls| {
+                    let matching = calls.matching(input, self.key);
+                    if calls.discarded() > 0 && (exact || matching.is_empty()) {
+                        return Err(discarded_history_message(calls, input));
+                    }
+                    Ok(matching)
+                })?;
+
+                let verified = meets(matching.len());
+                if verified {
+                    S::with(calls, |calls| calls.mark_verified(&matching));
+                }
+                Ok::<_, String>(verified)
+            }),
+            Recorded::Inputs(inputs) => {
+                let inputs = inputs.lock().unwrap_or_else(PoisonError::into_inner);
+                Ok(meets(inputs.iter().filter(|other| *other == input).count()))
+            }
+        };
+
+        // Fail without holding the calls
+        verified.unwrap_or_else(|message| match self.poison() {
+            Some(panic) => panic!("{}{}", message, poison_note(&panic)),
+            None => panic!("{}", message),
+        })
+    }
+
+    /// The panic which poisoned the recorded calls, if any
+    fn poison(&self) -> Option<String> {
+        match &self.calls {
+            Recorded::Log(calls) => S::take_poison(calls),
+            Recorded::Inputs(_) => None,
+        }
+    }
+}
+
+impl<I> Validator<I, ()> {
+    pub fn new(calls: Arc<Mutex<Vec<I>>>, input: I) -> Validator<I, ()> {
+        Validator::recorded(Recorded::Inputs(calls), input)
+    }
+}
+
+impl<I, O> Validator<I, O> {
+    pub(crate) fn for_calls(calls: Calls<I, O>, input: I) -> Validator<I, O> {
+        Validator::recorded(Recorded::Log(calls), input)
+    }
+
+    fn recorded(calls: Recorded<Threaded, I, O>, input: I) -> Validator<I, O> {
+        Validator {
+            check: Check::recorded(calls, input),
+            expectations: Weak::new(),
+            signal: Arc::new(Signal::default()),
+        }
+    }
+
+    pub(crate) fn expected_by(mut self, expectations: &Arc<Expectations>) -> Validator<I, O> {
+        self.expectations = Arc::downgrade(expectations);
+        self
+    }
 
+    pub(crate) fn signaled_by(mut self, signal: &Arc<Signal>) -> Validator<I, O> {
+        self.signal = signal.clone();
+        self
+    }
+
+    pub(crate) fn signal(&self) -> &Arc<Signal> {
+        &self.signal
+    }
+}
+
+impl<I: PartialEq, O> Validator<I, O> {
+    pub fn result(&mut self) -> bool {
+        self.check.result()
+    }
+    pub fn times(mut self, times: usize) -> Validator<I, O> {
+        self.check.times(times);
         self
     }
     pub fn called(self) -> bool {
         let mut this = self;
         this.result()
     }
+
+    /// Block until the expectation holds or the timeout has passed, for calls
+    /// made by other threads
+    pub fn within(self, timeout: Duration) -> Validator<I, O> {
+        let deadline = Instant::now() + timeout;
+        if let Recorded::Log(calls) = &self.check.calls {
+            loop {
+                // Read the notifications before checking, so a call made in
+                // between still wakes us
+                let seen = self.signal.notified();
+                if calls.read(|calls| self.check.holds_for(calls)) || Instant::now() >= deadline {
+                    break;
+                }
+                self.signal.wait_until(seen, deadline);
+            }
+        }
+
+        self.settle()
+    }
+
+    /// Wait until the expectation holds or the timeout has passed, for calls
+    /// made by other tasks
+    pub fn eventually(self, timeout: Duration) -> Eventually<I, O> {
+        Eventually::new(self, Instant::now() + timeout)
+    }
+
+    pub(crate) fn holds(&self) -> bool {
+        match &self.check.calls {
+            Recorded::Log(calls) => calls.read(|calls| self.check.holds_for(calls)),
+            // Inputs recorded by hand are never signaled, so check them now
+            Recorded::Inputs(_) => true,
+        }
+    }
+
+    /// Evaluate the expectation against the calls recorded so far
+    pub(crate) fn settle(mut self) -> Validator<I, O> {
+        self.check.settle();
+        self
+    }
+}
+
+impl<I: PartialEq, O> Verify for Validator<I, O> {
+    fn called(self) -> bool {
+        Validator::called(self)
+    }
+}
+
+impl<I: PartialEq + std::fmt::Debug + Send + 'static, O: Send + 'static> Validator<I, O> {
+    /// Check the expectation when the last clone of the mock is dropped
+    /// instead of now
+    pub fn verify_on_drop(self) {
+        let expectations = self
+            .expectations
+            .upgrade()
+            .expect("Only validators created by a mock can be verified on drop");
+        let Check {
+            calls: Recorded::Log(calls),
+            input,
+            key,
+            times,
+            ..
+        } = self.check
+        else {
+            unreachable!("Validators created by a mock check its calls")
+        };
+
+        expectations.push(Box::new(move || {
+            calls.read(|calls| {
+                let matching = calls.matching(&input, key);
+                let times_called = matching.len();
+                if calls.discarded() > 0 && (times.is_some() || times_called == 0) {
+                    return Some(discarded_history_message(calls, &input));
+                }
+
+                match times {
+                    Some(times) if times != times_called => Some(format!(
+                        "{:?} expected {} times, called {} times{}",
+                        input,
+                        times,
+                        times_called,
+                        origins(calls, &matching)
+                    )),
+                    None if times_called == 0 => {
+                        Some(format!("{:?} expected, never called", input))
+                    }
+                    _ => None,
+                }
+            })
+        }));
+    }
+}
+
+/// Where the kept calls at the positions were made from
+fn origins<I, O>(calls: &CallLog<I, O>, positions: &[usize]) -> String {
+    let first = calls.discarded();
+    let origins: Vec<String> = positions
+        .iter()
+        .filter_map(|position| calls.get(first + position))
+        .map(Call::origin)
+        .collect();
+
+    match origins.is_empty() {
+        true => String::new(),
+        false => format!(" ({})", origins.join(", ")),
+    }
+}
+
+fn discarded_history_message<I, O>(calls: &CallLog<I, O>, input: &I) -> String {
+    let input = match calls.describe(input) {
+        Some(input) => format!(" with {}", input),
+        None => String::new(),
+    };
+    format!(
+        "Can't verify calls{}: {} of {} calls were discarded by the mock history",
+        input,
+        calls.discarded(),
+        calls.len()
+    )
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::call::Outcome;
+    use crate::sync::Reentrant;
     use table_test::table_test;
 
+    fn call(input: i32) -> Call<i32, ()> {
+        Call::new(
+            input,
+            Outcome::Returned {
+                output: (),
+                rule: 0,
+            },
+        )
+    }
+
+    /// The calls recorded by a mock called with the inputs
+    fn log(inputs: &[i32]) -> Calls<i32, ()> {
+        let calls = Arc::new(Reentrant::new(CallLog::default()));
+        for input in inputs {
+            calls.write(|calls| calls.record(call(*input), None));
+        }
+        calls
+    }
+
     #[test]
     fn times() {
         let table = vec![
@@ -69,7 +352,7 @@ mod test {
 
         for (test_case, (calls, initial_result, input, times), expected) in table_test!(table) {
             let mut validator = Validator::new(Arc::new(Mutex::new(calls.clone())), input);
-            validator.result = Some(initial_result);
+            validator.check.result = Some(initial_result);
 
             let actual = verify(validator.times(times));
 
@@ -84,4 +367,60 @@ mod test {
                 .assert_eq(expected, actual);
         }
     }
+
+    #[test]
+    fn inputs_need_no_debug_to_be_verified() {
+        #[derive(PartialEq)]
+        struct Opaque(i32);
+
+        let inputs = Arc::new(Mutex::new(vec![Opaque(1), Opaque(1)]));
+
+        assert!(verify(Validator::new(inputs.clone(), Opaque(1)).times(2)));
+        assert!(!verify(Validator::new(inputs, Opaque(2))));
+    }
+
+    #[test]
+    fn verifying_marks_matching_calls_as_verified() {
+        let calls = log(&[1, 2, 1]);
+
+        assert!(verify(Validator::for_calls(calls.clone(), 1)));
+
+        let verified: Vec<bool> = calls.read(|calls| calls.iter().map(Call::is_verified).collect());
+        assert_eq!(verified, vec![true, false, true]);
+    }
+
+    #[test]
+    fn failed_times_leaves_calls_unverified() {
+        let calls = log(&[1, 1]);
+
+        assert!(!verify(Validator::for_calls(calls.clone(), 1).times(3)));
+
+        let verified: Vec<bool> = calls.read(|calls| calls.iter().map(Call::is_verified).collect());
+        assert_eq!(verified, vec![false, false]);
+    }
+
+    #[test]
+    fn within_waits_for_calls_from_other_threads() {
+        let calls = Arc::new(Reentrant::new(CallLog::default()));
+        let signal = Arc::new(Signal::default());
+        let validator = Validator::for_calls(calls.clone(), 1).signaled_by(&signal);
+
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            calls.write(|calls| calls.record(call(1), None));
+            signal.notify();
+        });
+
+        assert!(verify(validator.within(Duration::from_secs(5))));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn within_fails_after_timeout() {
+        let calls = log(&[2]);
+
+        assert!(!verify(
+            Validator::for_calls(calls, 1).within(Duration::from_millis(10))
+        ));
+    }
 }
@@ -1,13 +1,13 @@
-use crate::rule::Rule;
-use std::sync::{Arc, Mutex};
+use crate::rule::{configure, Rules};
+use crate::storage::Threaded;
 
 pub struct When<I, O> {
     input: I,
-    rules: Arc<Mutex<Vec<Rule<I, O>>>>,
+    rules: Rules<Threaded, I, O>,
 }
 
 impl<I, O> When<I, O> {
-    pub(crate) fn new(input: I, rules: Arc<Mutex<Vec<Rule<I, O>>>>) -> Self {
+    pub(crate) fn new(input: I, rules: Rules<Threaded, I, O>) -> Self {
         When { input, rules }
     }
 }
@@ -16,20 +16,7 @@ impl<I: PartialEq, O> When<I, O> {
     /// Use the when return value when the mock is called with the specified
     /// input
     pub fn will_return(self, value: O) {
-        let mut rules_locked = self.rules.lock().unwrap();
-        let when_value = rules_locked
-            .iter()
-            .enumerate()
-            .find(|(_i, value)| value.input == self.input);
-
-        let rule = Rule::new(self.input, value);
-        match when_value {
-            Some((index, _value)) => {
-                let _old_rule = std::mem::replace(&mut rules_locked[index], rule);
-                ()
-            }
-            None => rules_locked.push(rule),
-        }
+        configure::<Threaded, _, _>(&self.rules, self.input, value)
     }
 }
 
@@ -43,33 +30,35 @@ impl<I: PartialEq, O: Default> When<I, O> {
 #[cfg(test)]
 mod tests {
     use super::When;
-    use crate::rule::Rule;
-    use std::sync::{Arc, Mutex};
+    use crate::rule::{Rule, RuleTable};
+    use crate::sync::Reentrant;
+    use std::sync::Arc;
 
     /// When `Given::will_return` is called with an output, the corresponding
     /// rule is added to the rules list
     #[test]
     fn add_rule_to_list() {
-        let rules = Arc::new(Mutex::new(Vec::new()));
+        let rules = Arc::new(Reentrant::new(RuleTable::new()));
         let when = When::new("hello", rules.clone());
 
         when.will_return(true);
 
-        let rules = rules.lock().unwrap();
-        assert_eq!(*rules, vec![Rule::new("hello", true)]);
+        rules.read(|rules| assert_eq!(rules.rules(), [Rule::new("hello", true)]));
     }
 
     #[test]
     fn when_input_already_match_another_rule_replace_old_rule() {
-        let rules = Arc::new(Mutex::new(Vec::new()));
+        let rules = Arc::new(Reentrant::new(RuleTable::new()));
         let when = When::new("sameinput", rules.clone());
 
         let assert_rule = |input, output| {
-            let rules_locked = rules.lock().unwrap();
-            let rule = rules_locked.get(0).unwrap();
-            assert_eq!(rules_locked.len(), 1, "Rules should have only one rule.");
-            assert_eq!(rule.input, input);
-            assert_eq!(rule.output, output);
+            rules.read(|rules| {
+                let rule = rules.rules().first().unwrap();
+                assert_eq!(rules.rules().len(), 1, "Rules should have only one rule.");
+                assert_eq!(rule.input, input);
+                rule.output
+                    .read(|rule_output| assert_eq!(*rule_output, output));
+            })
         };
 
         when.will_return("rule1");
@@ -84,12 +73,11 @@ mod tests {
     /// default of the output type
     #[test]
     fn add_default() {
-        let rules = Arc::new(Mutex::new(Vec::new()));
+        let rules = Arc::new(Reentrant::new(RuleTable::new()));
         let when: When<&str, bool> = When::new("hello", rules.clone());
 
         when.will_return_default();
 
-        let rules = rules.lock().unwrap();
-        assert_eq!(*rules, vec![Rule::new("hello", false)]);
+        rules.read(|rules| assert_eq!(rules.rules(), [Rule::new("hello", false)]));
     }
 }
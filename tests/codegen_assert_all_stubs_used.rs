@@ -0,0 +1,39 @@
+use mock_it::{any, eq, mock_it};
+
+#[mock_it]
+trait ATrait {
+    fn a_fn(&self, arg1: usize);
+    fn another_fn(&self, arg1: &str) -> String;
+}
+
+#[test]
+fn mock_without_rules_has_all_stubs_used() {
+    let mock = ATraitMock::new();
+
+    mock.assert_all_stubs_used();
+}
+
+#[test]
+fn mock_with_every_rule_hit_has_all_stubs_used() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(eq(42)).will_return(());
+    mock.when_another_fn(any())
+        .will_return("output".to_string());
+
+    mock.a_fn(42);
+    mock.another_fn("input");
+
+    mock.assert_all_stubs_used();
+}
+
+#[test]
+#[should_panic(expected = "Mock \"ATraitMock.a_fn\" has unused stubs: 7")]
+fn mock_with_unused_rule_should_panic() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(eq(42)).will_return(());
+    mock.when_a_fn(eq(7)).will_return(());
+
+    mock.a_fn(42);
+
+    mock.assert_all_stubs_used();
+}
@@ -0,0 +1,34 @@
+use mock_it::{any, mock_it};
+
+#[mock_it(NAME = "nurse", Output = Vec<usize>, MAX = 3)]
+trait Worker {
+    type Output;
+    const NAME: &'static str;
+    const MAX: usize;
+    const RETRIES: usize = 2;
+
+    fn work(&self, load: usize) -> Self::Output;
+}
+
+fn describe<W: Worker>(_worker: &W) -> String {
+    format!("{} {} {}", W::NAME, W::MAX, W::RETRIES)
+}
+
+#[test]
+fn mock_binds_associated_constants() {
+    let mock = WorkerMock::new();
+    mock.when_work(any()).will_return(vec![1]);
+
+    assert_eq!(describe(&mock), "nurse 3 2");
+    assert_eq!(mock.work(1), vec![1]);
+}
+
+#[mock_it(RETRIES = 5)]
+trait Retrying {
+    const RETRIES: usize = 2;
+}
+
+#[test]
+fn mock_can_override_default_constants() {
+    assert_eq!(<RetryingMock as Retrying>::RETRIES, 5);
+}
@@ -0,0 +1,37 @@
+use mock_it::{eq, mock_it};
+
+#[derive(Debug, Clone, PartialEq)]
+struct NotFound;
+
+#[mock_it(Output = String, Error = NotFound)]
+trait Repository {
+    type Output;
+    type Error;
+
+    fn find(&self, id: usize) -> Result<Self::Output, Self::Error>;
+    fn save(&self, value: &Self::Output) -> Option<Self::Error>;
+}
+
+fn find_in<R: Repository<Output = String>>(repository: &R, id: usize) -> Option<String> {
+    repository.find(id).ok()
+}
+
+#[test]
+fn mock_binds_associated_types() {
+    let mock = RepositoryMock::new();
+    mock.when_find(eq(1)).will_return(Ok("one".to_string()));
+    mock.when_find(eq(2)).will_return(Err(NotFound));
+
+    assert_eq!(find_in(&mock, 1), Some("one".to_string()));
+    assert_eq!(find_in(&mock, 2), None);
+}
+
+#[test]
+fn mock_binds_associated_types_in_reference_arguments() {
+    let mock = RepositoryMock::new();
+    let value = "one".to_string();
+    mock.when_save(eq(&value)).will_return(None);
+
+    assert_eq!(mock.save(&value), None);
+    assert!(mock.expect_save(eq(&value)).called());
+}
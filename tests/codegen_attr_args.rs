@@ -0,0 +1,54 @@
+use mock_it::{eq, mock_it};
+
+mod hospital {
+    use mock_it::mock_it;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct Patient(pub u32);
+
+    #[mock_it(name = "FakeNurse", vis = "pub(crate)")]
+    pub(crate) trait Nurse {
+        fn care_for(&self, patient: Patient) -> bool;
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Room(u32);
+
+    #[mock_it]
+    trait Cleaner {
+        fn clean(&self, room: Room);
+    }
+
+    #[test]
+    fn mock_mirrors_the_trait_visibility() {
+        let mock = CleanerMock::new();
+        mock.when_clean(mock_it::any()).will_return(());
+
+        mock.clean(Room(1));
+
+        assert!(mock.expect_clean(mock_it::eq(Room(1))).called());
+    }
+}
+
+#[mock_it(name = "PublicFake", vis = "pub")]
+trait APrivateTrait {
+    fn a_fn(&self) -> usize;
+}
+
+#[test]
+fn mock_can_be_renamed() {
+    use hospital::{FakeNurse, Nurse, Patient};
+
+    let mock = FakeNurse::new();
+    mock.when_care_for(eq(Patient(1))).will_return(true);
+
+    assert!(mock.care_for(Patient(1)));
+}
+
+#[test]
+#[should_panic(expected = "Mock \"PublicFake.a_fn\" called with unexpected input")]
+fn renamed_mock_uses_its_name_in_messages() {
+    let mock = PublicFake::new();
+
+    mock.a_fn();
+}
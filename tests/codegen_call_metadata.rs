@@ -0,0 +1,56 @@
+use mock_it::{any, mock_it};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::thread;
+use std::time::Duration;
+
+#[mock_it]
+trait ATrait {
+    fn a_fn(&self, arg: usize);
+}
+
+#[test]
+fn mock_records_where_the_call_was_made_from() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+
+    mock.a_fn(1);
+    let line = line!() - 1;
+
+    let record = mock.a_fn.last_call_record().unwrap();
+    assert_eq!(record.location.file(), file!());
+    assert_eq!(record.location.line(), line);
+}
+
+#[test]
+fn mock_records_when_and_from_which_thread_the_call_was_made() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+
+    mock.a_fn(1);
+    thread::sleep(Duration::from_millis(10));
+    let worker = mock.clone();
+    thread::Builder::new()
+        .name("worker".to_string())
+        .spawn(move || worker.a_fn(2))
+        .unwrap()
+        .join()
+        .unwrap();
+
+    let records = mock.call_records_a_fn();
+    assert!(records[1].at - records[0].at >= Duration::from_millis(10));
+    assert_eq!(records[0].thread, thread::current().id());
+    assert_eq!(records[1].thread_name.as_deref(), Some("worker"));
+}
+
+#[test]
+fn unverified_calls_show_where_they_were_made_from() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+    mock.a_fn(1);
+    let line = line!() - 1;
+
+    let result = catch_unwind(AssertUnwindSafe(|| mock.verify_no_more_interactions()));
+
+    let message = *result.unwrap_err().downcast::<String>().unwrap();
+    assert!(message.contains(&format!("1 (called at {}:{}:", file!(), line)));
+}
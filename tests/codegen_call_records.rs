@@ -0,0 +1,78 @@
+use mock_it::{eq, mock_it, Call, Outcome, Validator};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+#[mock_it]
+trait ATrait {
+    fn a_fn(&self, arg: usize) -> String;
+}
+
+/// An output which can't be cloned
+#[derive(Debug)]
+pub struct Fragile;
+
+impl Clone for Fragile {
+    fn clone(&self) -> Fragile {
+        panic!("fragile clone")
+    }
+}
+
+#[mock_it]
+trait FragileTrait {
+    fn a_fn(&self, arg: usize) -> Fragile;
+}
+
+#[test]
+fn mock_records_the_output_and_the_matched_rule() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(eq(1)).will_return("one".to_string());
+    mock.when_a_fn(eq(2)).will_return("two".to_string());
+
+    mock.a_fn(1);
+    mock.a_fn(2);
+
+    let records = mock.call_records_a_fn();
+    assert_eq!(records[0].output(), Some(&"one".to_string()));
+    assert_eq!(records[0].rule(), Some(0));
+    assert_eq!(records[1].input, eq(2));
+    assert_eq!(records[1].output(), Some(&"two".to_string()));
+    assert_eq!(records[1].rule(), Some(1));
+}
+
+#[test]
+fn mock_records_unexpected_calls_as_panicked() {
+    let mock = ATraitMock::new();
+
+    let result = catch_unwind(AssertUnwindSafe(|| mock.a_fn(2)));
+
+    assert!(result.is_err());
+    let record = mock.a_fn.last_call_record().unwrap();
+    assert!(record.panicked());
+    match record.outcome {
+        Outcome::Panicked(message) => assert!(message.contains("unexpected input: \"2\"")),
+        Outcome::Returned { .. } => panic!("the call should have panicked"),
+    }
+}
+
+#[test]
+fn mock_records_panics_while_cloning_outputs() {
+    let mock = FragileTraitMock::new();
+    mock.when_a_fn(eq(1)).will_return(Fragile);
+
+    let result = catch_unwind(AssertUnwindSafe(|| mock.a_fn(1)));
+
+    assert!(result.is_err());
+    let record = mock.a_fn.last_call_record().unwrap();
+    match record.outcome {
+        Outcome::Panicked(message) => assert_eq!(message, "fragile clone"),
+        Outcome::Returned { .. } => panic!("the call should have panicked"),
+    }
+}
+
+#[test]
+fn types_without_output_default_to_unit() {
+    let validator: Validator<usize> = Validator::new(Default::default(), 1);
+    let calls: Vec<Call<usize>> = Vec::new();
+
+    assert!(!validator.called());
+    assert!(calls.is_empty());
+}
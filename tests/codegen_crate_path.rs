@@ -0,0 +1,87 @@
+mod support {
+    pub use ::mock_it as mocking;
+}
+
+mod renamed {
+    use crate::support::mocking::{eq, mock_it};
+
+    #[mock_it(crate = "crate::support::mocking")]
+    trait ATrait {
+        fn a_fn(&self, arg: &str) -> usize;
+    }
+
+    #[test]
+    fn mock_uses_the_configured_crate_path() {
+        let mock = ATraitMock::new();
+        mock.when_a_fn(eq("a")).will_return(1);
+
+        assert_eq!(mock.a_fn("a"), 1);
+        assert!(mock.expect_a_fn(eq("a")).called());
+    }
+}
+
+mod shadowed {
+    use ::mock_it::{any, mock_it};
+
+    #[allow(dead_code)]
+    mod mock_it {}
+
+    #[mock_it]
+    trait ATrait {
+        fn a_fn(&self, arg: usize) -> usize;
+    }
+
+    #[test]
+    fn mock_uses_absolute_paths_by_default() {
+        let mock = ATraitMock::new();
+        mock.when_a_fn(any()).will_return(1);
+
+        assert_eq!(mock.a_fn(2), 1);
+    }
+}
+
+mod prelude_shadowed {
+    #![allow(dead_code, unused_macros)]
+
+    use ::mock_it::{eq, mock_it};
+
+    trait Clone {}
+    trait Debug {}
+    trait Default {}
+    trait PartialEq {}
+    trait Send {}
+    trait Sync {}
+    struct Vec;
+    struct Option;
+    struct String;
+
+    macro_rules! format {
+        ($($tokens:tt)*) => {
+            compile_error!("the generated code uses the shadowed `format!`")
+        };
+    }
+
+    #[mock_it]
+    trait ATrait<T> {
+        fn a_fn(&self, arg: T) -> T;
+        fn b_fn(&self, arg: &str) -> ::core::option::Option<&str>;
+        fn generic_fn<
+            U: ::core::clone::Clone + ::core::marker::Send + ::core::marker::Sync + 'static,
+        >(
+            &self,
+        ) -> U;
+        fn static_fn() -> usize;
+    }
+
+    #[test]
+    fn mock_uses_absolute_paths_for_the_prelude() {
+        let mock = ATraitMock::<usize>::new();
+        mock.when_a_fn(eq(1)).will_return(2);
+        mock.when_generic_fn::<u8>().will_return(3);
+
+        assert_eq!(mock.a_fn(1), 2);
+        assert_eq!(mock.generic_fn::<u8>(), 3);
+        assert_eq!(mock.calls_a_fn(), [eq(1)]);
+        assert!(mock.expect_a_fn(eq(1)).called());
+    }
+}
@@ -1,6 +1,7 @@
 use mock_it::mock_it;
 
 #[mock_it]
+#[allow(dead_code)]
 trait ATrait {
     fn a_fn(&self, arg1: usize);
 }
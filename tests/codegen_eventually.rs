@@ -0,0 +1,69 @@
+use mock_it::{any, eq, mock_it};
+use std::time::Duration;
+
+#[mock_it]
+trait ATrait {
+    fn a_fn(&self, arg1: usize);
+}
+
+#[test]
+fn mock_can_verify_call_from_another_thread() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+
+    let a_trait = mock.clone();
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        a_trait.a_fn(42);
+        a_trait.a_fn(42);
+    });
+
+    assert!(mock
+        .expect_a_fn(eq(42))
+        .times(2)
+        .within(Duration::from_secs(5))
+        .called());
+    handle.join().unwrap();
+}
+
+#[test]
+fn mock_fails_verification_after_timeout() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+
+    assert!(!mock
+        .expect_a_fn(eq(42))
+        .within(Duration::from_millis(10))
+        .called());
+}
+
+#[tokio::test]
+async fn mock_can_verify_call_from_another_task() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+
+    let a_trait = mock.clone();
+    let handle = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        a_trait.a_fn(42);
+    });
+
+    assert!(mock
+        .expect_a_fn(eq(42))
+        .eventually(Duration::from_secs(5))
+        .await
+        .called());
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn mock_fails_async_verification_after_timeout() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+
+    assert!(!mock
+        .expect_a_fn(eq(42))
+        .eventually(Duration::from_millis(10))
+        .await
+        .called());
+}
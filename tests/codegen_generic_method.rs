@@ -0,0 +1,86 @@
+use mock_it::{any, eq, mock_it, verify};
+use std::cell::Cell;
+use std::fmt::Debug;
+
+#[derive(Debug, Clone, PartialEq)]
+struct User {
+    name: String,
+}
+
+#[mock_it]
+trait Store {
+    fn get<T: Clone + Debug + PartialEq + Send + 'static>(&self, key: &str) -> T;
+    fn put<K, V>(&self, key: K, value: V)
+    where
+        K: Clone + Debug + PartialEq + Send + 'static,
+        V: Clone + Debug + PartialEq + Send + 'static;
+}
+
+#[test]
+fn configure_each_instantiation() {
+    let store = StoreMock::new();
+    store.when_get::<User>(eq("user")).will_return(User {
+        name: "Jo".to_string(),
+    });
+    store.when_get::<u32>(eq("count")).will_return(3);
+
+    let user: User = store.get("user");
+    let count: u32 = store.get("count");
+
+    assert_eq!(user.name, "Jo");
+    assert_eq!(count, 3);
+    assert_eq!(store.call_count_get::<User>(), 1);
+    assert!(verify(store.expect_get::<u32>(eq("count"))));
+}
+
+#[test]
+fn inspect_several_type_parameters() {
+    let store = StoreMock::new();
+    store.when_put::<&str, u32>(any(), any()).will_return(());
+    store.when_put::<u8, u8>(any(), any()).will_return(());
+
+    store.put("a", 1u32);
+    store.put(1u8, 2u8);
+
+    assert_eq!(store.calls_put::<&str, u32>(), vec![(eq("a"), eq(1))]);
+    assert_eq!(store.calls_put::<u8, u8>(), vec![(eq(1), eq(2))]);
+}
+
+#[test]
+#[should_panic(expected = "Mock \"StoreMock.get::<u32>\" has unverified calls")]
+fn verify_every_instantiation() {
+    let store = StoreMock::new();
+    store.when_get::<u32>(any()).will_return(3);
+    let _: u32 = store.get("count");
+
+    store.verify_no_more_interactions();
+}
+
+#[test]
+#[should_panic(expected = "Mock \"StoreMock.get::<u64>\" called with unexpected input")]
+fn unconfigured_type_should_panic_with_its_name() {
+    let store = StoreMock::new();
+    store.when_get::<u32>(any()).will_return(3);
+
+    let _: u64 = store.get("count");
+}
+
+#[test]
+#[should_panic(expected = "key: expected \"count\", got \"other\" <- mismatch")]
+fn unexpected_input_should_name_the_arguments() {
+    let store = StoreMock::new();
+    store.when_get::<u32>(eq("count")).will_return(3);
+    let _: u32 = store.get("count");
+
+    let _: u32 = store.get("other");
+}
+
+#[test]
+fn outputs_need_not_be_sync() {
+    let store = StoreMock::new();
+    store.when_get::<Cell<u8>>(any()).will_return(Cell::new(3));
+
+    let cell: Cell<u8> = store.get("cell");
+
+    assert_eq!(cell.get(), 3);
+}
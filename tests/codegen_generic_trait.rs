@@ -1,3 +1,5 @@
+#![allow(clippy::let_unit_value)]
+
 mod simple {
     use mock_it::{any, eq, mock_it};
 
@@ -38,6 +40,7 @@ mod two_methods {
     use mock_it::{any, eq, mock_it};
 
     #[mock_it]
+    #[allow(dead_code)]
     trait ATrait<T> {
         fn a_fn(&self, arg1: T);
         fn another_fn(&self, arg1: &str);
@@ -145,3 +148,22 @@ mod output_not_partial_eq {
         assert!(mock.expect_a_fn(eq(42)).called());
     }
 }
+
+mod where_clause {
+    use mock_it::{eq, mock_it};
+
+    // Without a trailing comma after the existing predicate
+    #[rustfmt::skip]
+    #[mock_it]
+    trait ATrait<T> where T: Send {
+        fn a_fn(&self, arg1: T) -> T;
+    }
+
+    #[test]
+    fn mock_can_configure_will_return() {
+        let mock = ATraitMock::new();
+        mock.when_a_fn(eq(23)).will_return(42);
+
+        assert_eq!(mock.a_fn(23), 42);
+    }
+}
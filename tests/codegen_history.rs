@@ -0,0 +1,36 @@
+use mock_it::{any, eq, mock_it, History};
+
+#[mock_it]
+trait ATrait {
+    fn a_fn(&self, arg1: usize);
+}
+
+#[test]
+fn mock_can_bound_its_history() {
+    let mock = ATraitMock::new().with_history(History::Last(2));
+    mock.when_a_fn(any()).will_return(());
+
+    for arg in 0..1000 {
+        mock.a_fn(arg);
+    }
+
+    assert_eq!(mock.calls_a_fn(), vec![eq(998), eq(999)]);
+    assert_eq!(mock.call_count_a_fn(), 1000);
+    assert!(mock.expect_a_fn(eq(999)).called());
+}
+
+#[test]
+#[should_panic(
+    expected = "Can't verify calls with 3: 1000 of 1000 calls were discarded by the mock history"
+)]
+fn mock_counting_calls_only_should_panic_on_verification() {
+    let mock = ATraitMock::new().with_history(History::CountOnly);
+    mock.when_a_fn(any()).will_return(());
+
+    for arg in 0..1000 {
+        mock.a_fn(arg);
+    }
+
+    assert_eq!(mock.call_count_a_fn(), 1000);
+    mock.expect_a_fn(eq(3)).called();
+}
@@ -0,0 +1,65 @@
+use mock_it::{any, eq, mock_it, History};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+#[mock_it(indexed)]
+trait ATrait {
+    fn a_fn(&self, arg1: usize, arg2: String) -> usize;
+    fn generic_fn<T: Clone + Send + 'static>(&self, arg1: usize) -> T;
+    fn generic_arg<T>(&self, arg1: T, arg2: &str) -> usize
+    where
+        T: Clone + Debug + Eq + Hash + Send + 'static;
+    fn static_fn(arg1: usize) -> usize;
+}
+
+#[test]
+fn indexed_mock_answers_like_a_scanning_mock() {
+    let mock = ATraitMock::new();
+    for arg in 0..1000 {
+        mock.when_a_fn(eq(arg), eq(arg.to_string()))
+            .will_return(arg);
+    }
+    mock.when_a_fn(eq(1000), any()).will_return(0);
+
+    assert_eq!(mock.a_fn(5, "5".to_string()), 5);
+    assert_eq!(mock.a_fn(1000, "5".to_string()), 0);
+    assert_eq!(mock.a_fn(1000, "other".to_string()), 0);
+    assert_eq!(mock.a_fn(999, "999".to_string()), 999);
+    assert!(mock.expect_a_fn(eq(1000), any()).times(2).called());
+    assert!(mock
+        .expect_a_fn(any(), eq("5".to_string()))
+        .times(2)
+        .called());
+    assert!(mock.expect_a_fn(eq(999), eq("999".to_string())).called());
+    assert!(!mock.expect_a_fn(eq(6), eq("6".to_string())).called());
+}
+
+#[test]
+fn indexed_mock_verifies_its_kept_calls() {
+    let mock = ATraitMock::new().with_history(History::Last(2));
+    mock.when_a_fn(any(), any()).will_return(0);
+
+    for arg in 0..10 {
+        mock.a_fn(arg % 3, String::new());
+    }
+
+    assert!(mock.expect_a_fn(eq(0), eq(String::new())).called());
+    assert!(mock.expect_a_fn(eq(2), any()).called());
+    assert_eq!(mock.calls_a_fn().len(), 2);
+}
+
+#[test]
+fn indexed_generic_and_static_fns() {
+    let mock = ATraitMock::new();
+    mock.when_generic_fn::<String>(eq(1))
+        .will_return("one".to_string());
+    mock.when_generic_arg::<u8>(eq(1), eq("a")).will_return(2);
+    ATraitMock::when_static_fn_static(eq(1)).will_return(2);
+
+    assert_eq!(mock.generic_fn::<String>(1), "one");
+    assert_eq!(mock.generic_arg(1u8, "a"), 2);
+    assert_eq!(ATraitMock::static_fn(1), 2);
+    assert!(mock.expect_generic_fn::<String>(eq(1)).called());
+    assert!(mock.expect_generic_arg::<u8>(eq(1), any()).called());
+    assert!(ATraitMock::expect_static_fn_static(eq(1)).called());
+}
@@ -0,0 +1,33 @@
+use mock_it::{any, eq, mock_it};
+
+#[mock_it]
+trait ATrait {
+    fn a_fn(&self, arg1: usize, arg2: &str) -> bool;
+}
+
+#[test]
+fn mock_without_calls_has_nothing_to_inspect() {
+    let mock = ATraitMock::new();
+
+    assert_eq!(mock.calls_a_fn(), vec![]);
+    assert_eq!(mock.call_count_a_fn(), 0);
+    assert_eq!(mock.last_call_a_fn(), None);
+}
+
+#[test]
+fn mock_can_inspect_calls() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any(), any()).will_return(true);
+
+    mock.a_fn(1, "first");
+    mock.a_fn(2, "second");
+
+    assert_eq!(
+        mock.calls_a_fn(),
+        vec![(eq(1), eq("first".into())), (eq(2), eq("second".into()))]
+    );
+    assert_eq!(mock.call_count_a_fn(), 2);
+    assert_eq!(mock.last_call_a_fn(), Some((eq(2), eq("second".into()))));
+    assert_eq!(mock.nth_call_a_fn(0), Some((eq(1), eq("first".into()))));
+    assert_eq!(mock.nth_call_a_fn(2), None);
+}
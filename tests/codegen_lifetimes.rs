@@ -0,0 +1,64 @@
+#![allow(clippy::needless_lifetimes)]
+
+use mock_it::{any, eq, mock_it};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token<'a> {
+    text: &'a str,
+}
+
+#[mock_it]
+trait Parser<'a> {
+    fn parse(&self, input: &'a str) -> Token<'a>;
+    fn first(&self, token: Token<'a>) -> char;
+}
+
+#[mock_it]
+trait Lexer<'a> {
+    fn count(&self, input: &'a str) -> usize;
+}
+
+#[mock_it]
+trait Formatter {
+    fn width<'a>(&self, input: &'a str) -> usize;
+    fn token<'a, 'b>(&self, input: &'a str, other: &'b str) -> Token<'a>;
+}
+
+#[test]
+fn trait_lifetimes() {
+    let parser = ParserMock::new();
+    parser.when_parse(eq("a")).will_return(Token { text: "a" });
+    parser.when_first(any()).will_return('a');
+
+    let input = String::from("a");
+    let token = parser.parse(&input);
+
+    assert_eq!(token, Token { text: "a" });
+    assert_eq!(parser.first(token), 'a');
+}
+
+#[test]
+fn unused_trait_lifetimes() {
+    let lexer = LexerMock::new();
+    lexer.when_count(any()).will_return(2);
+
+    assert_eq!(lexer.count("ab"), 2);
+}
+
+#[test]
+fn method_lifetimes() {
+    let formatter = FormatterMock::new();
+    formatter.when_width(eq("abc")).will_return(3);
+    formatter
+        .when_token(any(), any())
+        .will_return(Token { text: "t" });
+
+    let input = String::from("abc");
+
+    assert_eq!(formatter.width(&input), 3);
+    assert_eq!(formatter.token(&input, "b"), Token { text: "t" });
+    assert_eq!(
+        formatter.calls_width(),
+        vec![eq(std::sync::Arc::from("abc"))]
+    );
+}
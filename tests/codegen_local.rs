@@ -0,0 +1,52 @@
+use mock_it::{any, eq, mock_it, verify};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[mock_it(local)]
+trait ATrait {
+    fn a_fn(&self, arg1: Rc<usize>) -> Rc<RefCell<String>>;
+}
+
+#[test]
+#[should_panic]
+fn mock_no_when_should_panic() {
+    let mock = ATraitMock::new();
+    let _output = mock.a_fn(Rc::new(23));
+}
+
+#[test]
+fn mock_can_configure_will_return() {
+    let mock = ATraitMock::new();
+    let output = Rc::new(RefCell::new("output".to_string()));
+    mock.when_a_fn(eq(Rc::new(23))).will_return(output.clone());
+
+    let actual = mock.a_fn(Rc::new(23));
+
+    assert!(Rc::ptr_eq(&actual, &output));
+}
+
+#[test]
+fn mock_can_verify_called_with() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any())
+        .will_return(Rc::new(RefCell::new(String::new())));
+
+    let _output = mock.a_fn(Rc::new(42));
+
+    assert!(mock.expect_a_fn(eq(Rc::new(42))).times(1).called());
+    assert_eq!(mock.calls_a_fn(), vec![eq(Rc::new(42))]);
+    mock.verify_no_more_interactions();
+    mock.assert_all_stubs_used();
+}
+
+#[test]
+fn mock_can_be_verified_like_a_thread_safe_mock() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any())
+        .will_return(Rc::new(RefCell::new(String::new())));
+
+    let _output = mock.a_fn(Rc::new(42));
+
+    assert!(verify(mock.expect_a_fn(eq(Rc::new(42)))));
+    assert!(!verify(mock.expect_a_fn(eq(Rc::new(23)))));
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::let_unit_value)]
+
 use mock_it::{any, eq, mock_it};
 
 #[mock_it]
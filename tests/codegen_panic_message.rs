@@ -1,4 +1,6 @@
-use mock_it::mock_it;
+#![allow(clippy::let_unit_value)]
+
+use mock_it::{eq, mock_it};
 
 #[mock_it]
 trait ATrait {
@@ -13,3 +15,23 @@ fn mock_no_when_should_panic_with_right_message() {
     let mock = ATraitMock::new();
     let _output = mock.a_fn(23);
 }
+
+#[mock_it]
+trait AnotherTrait {
+    fn another_fn(&self, id: usize, name: &str) -> bool;
+}
+
+#[test]
+#[should_panic(expected = "Configured rules:
+    (1, \"a\")
+    (2, \"b\")
+Closest rule: (2, \"b\")
+    id: 2
+    name: expected \"b\", got \"c\" <- mismatch")]
+fn mock_unexpected_input_should_show_the_closest_rule() {
+    let mock = AnotherTraitMock::new();
+    mock.when_another_fn(eq(1), eq("a")).will_return(true);
+    mock.when_another_fn(eq(2), eq("b")).will_return(true);
+
+    mock.another_fn(2, "c");
+}
@@ -0,0 +1,56 @@
+use mock_it::{eq, mock_it};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+#[mock_it]
+trait ATrait {
+    fn a_fn(&self, arg1: usize) -> String;
+}
+
+#[test]
+fn mock_keeps_working_after_unexpected_call() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(eq(1)).will_return("one".to_string());
+
+    let result = catch_unwind(AssertUnwindSafe(|| mock.a_fn(2)));
+    assert!(result.is_err());
+
+    mock.when_a_fn(eq(2)).will_return("two".to_string());
+    assert_eq!(mock.a_fn(1), "one");
+    assert_eq!(mock.a_fn(2), "two");
+    assert!(mock.expect_a_fn(eq(2)).times(2).called());
+}
+
+/// An argument whose comparison panics, while the mock reads its rules
+#[derive(Debug, Clone)]
+struct Flaky(usize);
+
+impl PartialEq for Flaky {
+    fn eq(&self, other: &Flaky) -> bool {
+        if self.0 == 13 || other.0 == 13 {
+            panic!("unlucky comparison");
+        }
+        self.0 == other.0
+    }
+}
+
+#[mock_it]
+trait FlakyTrait {
+    fn a_fn(&self, arg1: Flaky) -> String;
+}
+
+#[test]
+fn panicking_comparisons_do_not_poison_the_mock() {
+    let mock = FlakyTraitMock::new();
+    mock.when_a_fn(eq(Flaky(1))).will_return("one".to_string());
+
+    let result = catch_unwind(AssertUnwindSafe(|| mock.a_fn(Flaky(13))));
+    assert!(result.is_err());
+    assert_eq!(mock.a_fn(Flaky(1)), "one");
+
+    // The rules were only read by the comparison, so they are consistent
+    let failure = catch_unwind(AssertUnwindSafe(|| mock.verify_no_more_interactions()))
+        .expect_err("the call is unverified");
+    let message = failure.downcast_ref::<String>().unwrap();
+    assert!(message.contains("has unverified calls"), "{}", message);
+    assert!(!message.contains("poisoned"), "{}", message);
+}
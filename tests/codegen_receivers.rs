@@ -0,0 +1,65 @@
+use mock_it::{any, eq, mock_it, verify};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Report {
+    lines: usize,
+}
+
+#[mock_it]
+trait Builder {
+    fn line(&mut self, text: String);
+    fn finish(self) -> Report;
+    fn boxed(self: Box<Self>) -> usize;
+    fn shared(self: Arc<Self>) -> usize;
+    fn pinned(self: Pin<&mut Self>) -> usize;
+}
+
+#[mock_it(local)]
+trait Machine {
+    fn next(self: Rc<Self>) -> bool;
+    fn stop(self) -> bool;
+}
+
+#[test]
+fn consuming_receivers_share_the_mock_state() {
+    let builder = BuilderMock::new();
+    builder.when_line(any()).will_return(());
+    builder.when_finish().will_return(Report { lines: 1 });
+
+    let mut consumed = builder.clone();
+    consumed.line("a".to_string());
+    let report = consumed.finish();
+
+    assert_eq!(report, Report { lines: 1 });
+    assert!(verify(builder.expect_line(eq("a".to_string()))));
+    assert!(verify(builder.expect_finish().times(1)));
+}
+
+#[test]
+fn smart_pointer_receivers() {
+    let builder = BuilderMock::new();
+    builder.when_boxed().will_return(1);
+    builder.when_shared().will_return(2);
+    builder.when_pinned().will_return(3);
+
+    let mut pinned = builder.clone();
+
+    assert_eq!(Box::new(builder.clone()).boxed(), 1);
+    assert_eq!(Arc::new(builder.clone()).shared(), 2);
+    assert_eq!(Pin::new(&mut pinned).pinned(), 3);
+    assert_eq!(builder.call_count_pinned(), 1);
+}
+
+#[test]
+fn local_consuming_receivers() {
+    let machine = MachineMock::new();
+    machine.when_next().will_return(true);
+    machine.when_stop().will_return(false);
+
+    assert!(Rc::new(machine.clone()).next());
+    assert!(!machine.clone().stop());
+    assert_eq!(machine.call_count_stop(), 1);
+}
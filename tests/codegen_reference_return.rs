@@ -0,0 +1,93 @@
+#![allow(clippy::needless_lifetimes)]
+
+use mock_it::{any, eq, mock_it, verify};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Port {
+    number: u16,
+}
+
+#[mock_it]
+trait Config {
+    fn name(&self) -> &str;
+    fn port(&self) -> &Port;
+    fn value(&self, key: &str) -> Option<&str>;
+    fn ports<'a>(&'a self) -> &'a [u16];
+    fn version(&self) -> &'static str;
+}
+
+#[mock_it(local)]
+trait LocalConfig {
+    fn name(&self) -> &str;
+}
+
+#[test]
+fn return_references_to_the_configured_values() {
+    let config = ConfigMock::new();
+    config.when_name().will_return(Arc::from("server"));
+    config
+        .when_port()
+        .will_return(Arc::new(Port { number: 80 }));
+    config
+        .when_value(eq("host"))
+        .will_return(Some(Arc::from("localhost")));
+    config.when_value(eq("user")).will_return(None);
+    config.when_ports().will_return(Arc::from(vec![80, 443]));
+    config.when_version().will_return("1.0");
+
+    assert_eq!(config.name(), "server");
+    assert_eq!(config.port(), &Port { number: 80 });
+    assert_eq!(config.value("host"), Some("localhost"));
+    assert_eq!(config.value("user"), None);
+    assert_eq!(config.ports(), &[80, 443]);
+    assert_eq!(config.version(), "1.0");
+    assert!(verify(config.expect_value(eq("host"))));
+}
+
+#[test]
+fn references_outlive_new_rules() {
+    let config = ConfigMock::new();
+    config.when_name().will_return(Arc::from("first"));
+    let name = config.name();
+
+    config.when_name().will_return(Arc::from("second"));
+
+    assert_eq!(name, "first");
+    assert_eq!(config.name(), "second");
+}
+
+#[test]
+fn local_mock_return_references() {
+    let config = LocalConfigMock::new();
+    config.when_name().will_return(Arc::from("local"));
+
+    assert_eq!(config.name(), "local");
+    assert_eq!(
+        config.call_records_name()[0].output(),
+        Some(&Arc::from("local"))
+    );
+}
+
+#[test]
+#[should_panic(expected = "Mock \"ConfigMock.value\" called with unexpected input")]
+fn unconfigured_reference_return_should_panic() {
+    let config = ConfigMock::new();
+    config.when_value(eq("host")).will_return(None);
+    config.when_name().will_return(Arc::from("server"));
+    let _ = config.name();
+
+    config.value("port");
+}
+
+#[test]
+fn borrowed_values_are_shared_by_clones() {
+    let config = ConfigMock::new();
+    config
+        .when_value(any())
+        .will_return(Some(Arc::from("value")));
+
+    let clone = config.clone();
+
+    assert_eq!(clone.value("key"), Some("value"));
+}
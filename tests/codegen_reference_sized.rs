@@ -2,6 +2,7 @@ use mock_it::{any, eq};
 
 #[cfg_attr(test, mock_it::mock_it)]
 pub trait ATrait {
+    #[allow(clippy::ptr_arg)]
     fn a_fn(&self, sized1: &usize, sized2: &String) -> String;
 }
 
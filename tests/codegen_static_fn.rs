@@ -0,0 +1,133 @@
+use mock_it::{any, eq, mock_it, verify, StaticMocks};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Config {
+    size: usize,
+}
+
+#[mock_it]
+trait Service {
+    fn create(config: Config) -> Self;
+    fn default_size() -> usize;
+    fn size(&self) -> usize;
+}
+
+fn build<S: Service>(size: usize) -> S {
+    S::create(Config { size })
+}
+
+#[test]
+fn mock_static_functions() {
+    let service = ServiceMock::new();
+    service.when_size().will_return(3);
+    ServiceMock::when_create_static(any()).will_return(service);
+    ServiceMock::when_default_size_static().will_return(3);
+
+    let built: ServiceMock = build(ServiceMock::default_size());
+
+    assert_eq!(built.size(), 3);
+    assert!(verify(ServiceMock::expect_create_static(eq(Config {
+        size: 3
+    }))));
+    assert_eq!(ServiceMock::mock_create_static().call_count(), 1);
+}
+
+#[test]
+fn static_mocks_are_isolated_between_tests() {
+    assert_eq!(ServiceMock::mock_create_static().call_count(), 0);
+    assert_eq!(ServiceMock::mock_default_size_static().call_count(), 0);
+}
+
+#[test]
+#[should_panic(expected = "Mock \"ServiceMock.default_size\" called with unexpected input")]
+fn unconfigured_static_function_should_panic() {
+    ServiceMock::default_size();
+}
+
+#[test]
+#[should_panic(expected = "Mock \"ServiceMock.default_size\" has unverified calls")]
+fn static_functions_have_their_own_verification() {
+    ServiceMock::when_default_size_static().will_return(3);
+    ServiceMock::default_size();
+
+    ServiceMock::new().verify_no_more_interactions();
+    ServiceMock::verify_no_more_interactions_static();
+}
+
+#[test]
+#[should_panic(expected = "Mock \"ServiceMock.create\" has unused stubs")]
+fn static_functions_have_their_own_stub_check() {
+    ServiceMock::when_create_static(any()).will_return(ServiceMock::new());
+
+    ServiceMock::assert_all_stubs_used_static();
+}
+
+mod other {
+    use mock_it::mock_it;
+
+    #[mock_it]
+    pub trait Service {
+        fn default_size() -> usize;
+    }
+}
+
+#[test]
+fn static_mocks_are_registered_by_module() {
+    ServiceMock::when_default_size_static().will_return(3);
+    other::ServiceMock::when_default_size_static().will_return(4);
+
+    assert_eq!(ServiceMock::default_size(), 3);
+    assert_eq!(<other::ServiceMock as other::Service>::default_size(), 4);
+}
+
+#[test]
+fn entered_static_mocks_are_fresh() {
+    ServiceMock::when_default_size_static().will_return(3);
+    ServiceMock::default_size();
+
+    let mocks = StaticMocks::new();
+    let entered = mocks.enter();
+    assert_eq!(ServiceMock::mock_default_size_static().call_count(), 0);
+
+    drop(entered);
+    assert_eq!(ServiceMock::mock_default_size_static().call_count(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn scoped_tasks_share_the_static_mocks() {
+    let mocks = StaticMocks::new();
+    mocks
+        .scope(async { ServiceMock::when_default_size_static().will_return(3) })
+        .await;
+
+    let tasks: Vec<_> = (0..4)
+        .map(|_| tokio::spawn(mocks.scope(async { ServiceMock::default_size() })))
+        .collect();
+    let mut sizes = Vec::new();
+    for task in tasks {
+        sizes.push(task.await.unwrap());
+    }
+
+    assert_eq!(sizes, vec![3; 4]);
+    let calls = mocks
+        .scope(async { ServiceMock::mock_default_size_static().call_count() })
+        .await;
+    assert_eq!(calls, 4);
+}
+
+#[test]
+#[should_panic(expected = "Mock \"ServiceMock.default_size\" is kept for its thread")]
+fn per_thread_static_mocks_can_not_verify_on_drop() {
+    ServiceMock::expect_default_size_static().verify_on_drop();
+}
+
+#[test]
+#[should_panic(expected = "Mock \"ServiceMock.default_size\" dropped with unmet expectations")]
+fn entered_static_mocks_verify_on_drop() {
+    let mocks = StaticMocks::new();
+    let entered = mocks.enter();
+    ServiceMock::expect_default_size_static().verify_on_drop();
+
+    drop(entered);
+    drop(mocks);
+}
@@ -0,0 +1,58 @@
+use mock_it::{any, eq, mock_it};
+use std::time::Duration;
+
+#[mock_it]
+trait ATrait {
+    fn a_fn(&self, arg1: usize);
+}
+
+#[test]
+fn mock_subscription_receives_new_calls() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+    mock.a_fn(1);
+
+    let mut subscription = mock.subscribe_a_fn();
+    mock.a_fn(2);
+
+    assert_eq!(subscription.try_next(), Some(eq(2)));
+    assert_eq!(subscription.try_next(), None);
+}
+
+#[tokio::test]
+async fn mock_can_await_next_call_from_another_task() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+    let mut subscription = mock.subscribe_a_fn();
+
+    let a_trait = mock.clone();
+    let handle = tokio::spawn(async move {
+        for arg in 0..3 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            a_trait.a_fn(arg);
+        }
+    });
+
+    for arg in 0..3 {
+        assert_eq!(subscription.next_call().await, eq(arg));
+    }
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn mock_can_await_next_call() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+
+    let a_trait = mock.clone();
+    let handle = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        a_trait.a_fn(42);
+    });
+
+    let input = tokio::time::timeout(Duration::from_secs(5), mock.next_call_a_fn())
+        .await
+        .unwrap();
+    assert_eq!(input, eq(42));
+    handle.await.unwrap();
+}
@@ -0,0 +1,44 @@
+use mock_it::{any, eq, mock_it};
+
+#[mock_it]
+trait ATrait {
+    fn a_fn(&self, arg1: usize);
+    fn another_fn(&self, arg1: &str) -> String;
+}
+
+#[test]
+fn mock_without_calls_has_no_more_interactions() {
+    let mock = ATraitMock::new();
+
+    mock.verify_no_more_interactions();
+}
+
+#[test]
+fn mock_with_every_call_verified_has_no_more_interactions() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+    mock.when_another_fn(any())
+        .will_return("output".to_string());
+
+    mock.a_fn(42);
+    mock.another_fn("input");
+
+    assert!(mock.expect_a_fn(eq(42)).called());
+    assert!(mock.expect_another_fn(any()).times(1).called());
+    mock.verify_no_more_interactions();
+}
+
+#[test]
+#[should_panic(expected = "Mock \"ATraitMock.another_fn\" has unverified calls: \"input\"")]
+fn mock_with_unverified_call_should_panic() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+    mock.when_another_fn(any())
+        .will_return("output".to_string());
+
+    mock.a_fn(42);
+    mock.another_fn("input");
+
+    assert!(mock.expect_a_fn(eq(42)).called());
+    mock.verify_no_more_interactions();
+}
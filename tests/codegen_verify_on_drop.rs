@@ -0,0 +1,27 @@
+use mock_it::{any, eq, mock_it};
+
+#[mock_it]
+trait ATrait {
+    fn a_fn(&self, arg1: usize);
+}
+
+#[test]
+fn mock_dropped_with_expectations_met() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+    mock.expect_a_fn(eq(42)).times(1).verify_on_drop();
+
+    mock.a_fn(42);
+}
+
+#[test]
+#[should_panic(
+    expected = "Mock \"ATraitMock.a_fn\" dropped with unmet expectations: 42 expected, never called"
+)]
+fn mock_dropped_with_expectations_not_met_should_panic() {
+    let mock = ATraitMock::new();
+    mock.when_a_fn(any()).will_return(());
+    mock.expect_a_fn(eq(42)).verify_on_drop();
+
+    mock.a_fn(7);
+}
@@ -0,0 +1,8 @@
+use mock_it::mock_it;
+
+#[mock_it]
+trait ATrait {
+    fn a_fn(&self, (left, right): (usize, usize));
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use mock_it::mock_it;
+
+#[mock_it]
+trait ATrait {
+    const NAME: &'static str;
+}
+
+fn main() {}
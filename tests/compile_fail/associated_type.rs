@@ -0,0 +1,15 @@
+use mock_it::mock_it;
+
+#[mock_it]
+trait ATrait {
+    type Output;
+
+    fn a_fn(&self) -> Self::Output;
+}
+
+#[mock_it(Input = usize)]
+trait AnotherTrait {
+    fn another_fn(&self);
+}
+
+fn main() {}
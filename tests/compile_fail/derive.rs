@@ -0,0 +1,13 @@
+use mock_it::mock_it;
+
+#[mock_it(derive(PartialEq, Clone))]
+trait ATrait {
+    fn a_fn(&self);
+}
+
+#[mock_it(derive(std::fmt::Debug))]
+trait AnotherTrait {
+    fn another_fn(&self);
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use mock_it::mock_it;
+
+#[mock_it]
+trait ATrait {
+    fn get<T: Clone + 'static>(&self) -> &T;
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use mock_it::mock_it;
+
+#[mock_it]
+trait ATrait {
+    fn create<T: Clone + 'static>() -> T;
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use mock_it::mock_it;
+
+struct Token<'a> {
+    text: &'a str,
+}
+
+#[mock_it]
+trait ATrait {
+    fn take<'a>(&self, token: Token<'a>) -> usize;
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use mock_it::mock_it;
+
+#[mock_it]
+struct AStruct {
+    field: usize,
+}
+
+fn main() {}
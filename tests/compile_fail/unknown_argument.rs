@@ -0,0 +1,8 @@
+use mock_it::mock_it;
+
+#[mock_it(remote)]
+trait ATrait {
+    fn a_fn(&self);
+}
+
+fn main() {}
@@ -0,0 +1,55 @@
+use mock_it::{mock_fn, verify, Mock};
+
+fn next_ids(mut callback: impl FnMut() -> u32) -> Vec<u32> {
+    vec![callback(), callback()]
+}
+
+fn notify(callback: &dyn Fn(String)) {
+    callback("done".to_string());
+}
+
+fn fold(numbers: &[i32], mut callback: impl FnMut(i32, i32) -> i32) -> i32 {
+    numbers.iter().fold(0, |acc, number| callback(acc, *number))
+}
+
+#[test]
+fn mock_fn_without_argument() {
+    let mock: Mock<(), u32> = Mock::new("next_id".to_string());
+    mock.when(()).will_return(7);
+
+    assert_eq!(next_ids(mock_fn!(mock, ||)), vec![7, 7]);
+    assert!(verify(mock.was_called_with(()).times(2)));
+}
+
+#[test]
+fn mock_fn_with_one_argument() {
+    let mock: Mock<String, ()> = Mock::new("notify".to_string());
+    mock.when("done".to_string()).will_return(());
+
+    notify(&mock_fn!(mock, |message: String|));
+
+    assert!(verify(mock.was_called_with("done".to_string())));
+}
+
+#[test]
+fn mock_fn_with_several_arguments() {
+    let mock: Mock<(i32, i32), i32> = Mock::new("add".to_string());
+    mock.when((0, 1)).will_return(10);
+    mock.when((10, 2)).will_return(30);
+
+    let sum = fold(&[1, 2], mock_fn!(mock, |acc: i32, number: i32|));
+
+    assert_eq!(sum, 30);
+    assert_eq!(mock.calls(), vec![(0, 1), (10, 2)]);
+}
+
+#[test]
+fn as_fn_can_be_boxed() {
+    let mock: Mock<i32, i32> = Mock::new("double".to_string());
+    mock.when(2).will_return(4);
+
+    let callback: Box<dyn Fn(i32) -> i32 + Send> = Box::new(mock.as_fn());
+
+    assert_eq!(callback(2), 4);
+    assert!(verify(mock.was_called_with(2)));
+}